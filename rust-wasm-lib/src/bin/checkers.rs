@@ -0,0 +1,427 @@
+//! Plays and analyzes international draughts in the terminal.
+//!
+//! Run with `cargo run --bin checkers -- --help`.
+
+use rust_wasm_lib::{move_notation, Color, Difficulty, Game, GameOutcome, Move, SearchResult};
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+use std::{env, fs, process};
+
+const USAGE: &str = "\
+Usage: checkers [OPTIONS]
+
+Options:
+    --engine <white|black|none>   side played by the engine (default: black)
+    --level <level>               beginner, easy, medium, hard or expert (default: medium)
+    --fen <FEN>                   start from a position like W:W31,32:B1,2
+    --ascii                       draw figures as letters instead of Unicode
+    --help                        print this help";
+
+const COMMANDS: &str = "\
+Commands:
+    32-28, 19x30x39   play a move in standard notation
+    moves             list the legal moves
+    undo              take back the last move, or your move and the engine answer
+    hint              search the best move for the side to move
+    go                let the engine move for the side to move
+    fen [FEN]         print the position, or set up the given one
+    pdn               print the game
+    pdn save <file>   write the game to a PDN file
+    pdn load <file>   read the first game of a PDN file
+    new               start a new game
+    help              print this help
+    quit              leave";
+
+//Time the engine gets for a hint
+const HINT_TIME: Duration = Duration::from_secs(2);
+
+struct Options {
+    engine: Option<Color>,
+    level: Difficulty,
+    fen: Option<String>,
+    unicode: bool,
+}
+
+//Options to play with, none when only the help was asked for
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        engine: Some(Color::Black),
+        level: Difficulty::Medium,
+        fen: None,
+        unicode: true,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--engine" => {
+                options.engine = match value()?.as_str() {
+                    "white" => Some(Color::White),
+                    "black" => Some(Color::Black),
+                    "none" => None,
+                    other => return Err(format!("unknown engine side {other}")),
+                }
+            }
+            "--level" => options.level = parse_level(&value()?)?,
+            "--fen" => options.fen = Some(value()?),
+            "--ascii" => options.unicode = false,
+            "--help" => return Ok(None),
+            other => return Err(format!("unknown option {other}\n\n{USAGE}")),
+        }
+    }
+    Ok(Some(options))
+}
+
+fn parse_level(text: &str) -> Result<Difficulty, String> {
+    match text {
+        "beginner" => Ok(Difficulty::Beginner),
+        "easy" => Ok(Difficulty::Easy),
+        "medium" => Ok(Difficulty::Medium),
+        "hard" => Ok(Difficulty::Hard),
+        "expert" => Ok(Difficulty::Expert),
+        other => Err(format!("unknown level {other}")),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Move(String),
+    Moves,
+    Undo,
+    Hint,
+    Go,
+    Fen(Option<String>),
+    Pdn,
+    PdnSave(String),
+    PdnLoad(String),
+    New,
+    Help,
+    Quit,
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let command = match words.as_slice() {
+        ["moves"] => Command::Moves,
+        ["undo"] => Command::Undo,
+        ["hint"] => Command::Hint,
+        ["go"] => Command::Go,
+        ["fen"] => Command::Fen(None),
+        ["fen", fen @ ..] => Command::Fen(Some(fen.join(" "))),
+        ["pdn"] => Command::Pdn,
+        ["pdn", "save", file] => Command::PdnSave(file.to_string()),
+        ["pdn", "load", file] => Command::PdnLoad(file.to_string()),
+        ["new"] => Command::New,
+        ["help"] => Command::Help,
+        ["quit"] | ["exit"] => Command::Quit,
+        [mov] if mov.starts_with(|c: char| c.is_ascii_digit()) => Command::Move(mov.to_string()),
+        _ => {
+            return Err(format!(
+                "unknown command \"{line}\", type help for the list"
+            ))
+        }
+    };
+    Ok(command)
+}
+
+/// Board as text, white at the bottom, with the square numbers of every row on the right.
+fn render(game: &Game, unicode: bool) -> String {
+    let symbol = |color: Color, king: bool| match (unicode, color, king) {
+        (true, Color::White, false) => '⛀',
+        (true, Color::White, true) => '⛁',
+        (true, Color::Black, false) => '⛂',
+        (true, Color::Black, true) => '⛃',
+        (false, Color::White, false) => 'w',
+        (false, Color::White, true) => 'W',
+        (false, Color::Black, false) => 'b',
+        (false, Color::Black, true) => 'B',
+    };
+    let frame = format!("+{}+\n", "-".repeat(21));
+    let mut text = frame.clone();
+    for row in 0..10 {
+        text.push('|');
+        for col in 0..10 {
+            let sqare_no = row * 10 + col;
+            let cell = if (row + col) % 2 == 0 {
+                ' '
+            } else {
+                match game.figure_map().get(&sqare_no) {
+                    Some(figure) => symbol(figure.color(), figure.is_king()),
+                    None => '.',
+                }
+            };
+            text.push(' ');
+            text.push(cell);
+        }
+        text.push_str(&format!(" |  {:>2}-{:>2}\n", row * 5 + 1, row * 5 + 5));
+    }
+    text.push_str(&frame);
+    text
+}
+
+fn status(game: &mut Game) -> String {
+    match game.outcome() {
+        GameOutcome::Ongoing => match game.side_to_move() {
+            Color::White => "White to move".to_string(),
+            Color::Black => "Black to move".to_string(),
+        },
+        GameOutcome::WhiteWins => "White wins".to_string(),
+        GameOutcome::BlackWins => "Black wins".to_string(),
+        GameOutcome::Draw { reason } => format!("Draw ({reason:?})"),
+    }
+}
+
+fn variation(moves: &[Vec<Move>]) -> String {
+    moves
+        .iter()
+        .map(|moves| move_notation(moves))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn describe(result: &SearchResult) -> String {
+    format!(
+        "{} (score {}, depth {}, {} nodes) {}",
+        move_notation(result.best_move()),
+        result.score(),
+        result.depth(),
+        result.nodes(),
+        variation(result.pv())
+    )
+}
+
+struct Session {
+    game: Game,
+    options: Options,
+}
+
+impl Session {
+    fn show(&mut self) {
+        print!("{}", render(&self.game, self.options.unicode));
+        println!("{}", status(&mut self.game));
+    }
+
+    //Engine answers as long as it is its turn
+    fn engine_moves(&mut self) {
+        while self.options.engine == Some(self.game.side_to_move())
+            && self.game.outcome() == GameOutcome::Ongoing
+        {
+            if !self.engine_move() {
+                break;
+            }
+        }
+    }
+
+    fn engine_move(&mut self) -> bool {
+        let moves = self.game.engine_move(self.options.level);
+        println!("Engine plays {}", move_notation(&moves));
+        match self.game.make_move(moves) {
+            Ok(()) => true,
+            Err(error) => {
+                println!("Engine move was rejected: {error}");
+                false
+            }
+        }
+    }
+
+    fn run(&mut self, command: Command) -> Result<bool, String> {
+        match command {
+            Command::Move(text) => {
+                let moves = self
+                    .game
+                    .parse_move(&text)
+                    .map_err(|error| error.to_string())?;
+                self.game
+                    .make_move(moves)
+                    .map_err(|error| error.to_string())?;
+                self.engine_moves();
+                self.show();
+            }
+            Command::Moves => {
+                let moves: Vec<String> = self
+                    .game
+                    .legal_moves()
+                    .iter()
+                    .map(|moves| move_notation(moves))
+                    .collect();
+                println!("{}", moves.join(" "));
+            }
+            Command::Undo => {
+                //Engine answer is taken back together with the move it answered
+                let plies = match self.options.engine {
+                    Some(engine) if engine != self.game.side_to_move() => 2,
+                    _ => 1,
+                };
+                if self.game.history().is_empty() {
+                    return Err("there is no move to take back".to_string());
+                }
+                if self.game.history().len() < plies {
+                    return Err("there is no move of yours to take back".to_string());
+                }
+                for _ in 0..plies {
+                    self.game.undo();
+                }
+                self.show();
+            }
+            Command::Hint => {
+                if self.game.outcome() != GameOutcome::Ongoing {
+                    return Err("game is over".to_string());
+                }
+                println!("{}", describe(&self.game.best_move_timed(HINT_TIME)));
+            }
+            Command::Go => {
+                if self.game.outcome() != GameOutcome::Ongoing {
+                    return Err("game is over".to_string());
+                }
+                if self.engine_move() {
+                    self.show();
+                }
+            }
+            Command::Fen(None) => println!("{}", self.game.to_fen()),
+            Command::Fen(Some(fen)) => {
+                self.game = Game::from_fen(&fen).map_err(|error| error.to_string())?;
+                self.show();
+            }
+            Command::Pdn => print!("{}", self.game.to_pdn()),
+            Command::PdnSave(file) => {
+                fs::write(&file, self.game.to_pdn()).map_err(|error| error.to_string())?;
+                println!("Game saved to {file}");
+            }
+            Command::PdnLoad(file) => {
+                let text = fs::read_to_string(&file).map_err(|error| error.to_string())?;
+                self.game = Game::from_pdn(&text).map_err(|error| error.to_string())?;
+                self.show();
+            }
+            Command::New => {
+                self.game = Game::new();
+                self.engine_moves();
+                self.show();
+            }
+            Command::Help => println!("{COMMANDS}"),
+            Command::Quit => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            process::exit(2);
+        }
+    };
+    let game = match &options.fen {
+        Some(fen) => Game::from_fen(fen).unwrap_or_else(|error| {
+            eprintln!("{error}");
+            process::exit(2);
+        }),
+        None => Game::new(),
+    };
+    let mut session = Session { game, options };
+    println!("Type help for the list of commands.");
+    session.engine_moves();
+    session.show();
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_command(line.trim()).and_then(|command| session.run(command)) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(message) => println!("{message}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_takes_back_the_engine_answer_with_the_move() {
+        let mut session = Session {
+            game: Game::new(),
+            options: parse_args(
+                ["--engine", "white", "--level", "beginner"]
+                    .iter()
+                    .map(|arg| arg.to_string()),
+            )
+            .unwrap()
+            .unwrap(),
+        };
+        session.engine_moves();
+        assert_eq!(session.game.history().len(), 1);
+        //Only the engine has moved yet
+        assert!(session.run(Command::Undo).is_err());
+        assert_eq!(session.game.history().len(), 1);
+
+        let reply = move_notation(&session.game.legal_moves()[0]);
+        assert_eq!(session.run(Command::Move(reply)), Ok(true));
+        assert_eq!(session.game.history().len(), 3);
+        assert_eq!(session.run(Command::Undo), Ok(true));
+        assert_eq!(session.game.history().len(), 1);
+        assert_eq!(session.game.side_to_move(), Color::Black);
+    }
+
+    #[test]
+    fn initial_board_has_twenty_figures_each() {
+        let text = render(&Game::new(), false);
+        assert_eq!(text.matches('b').count(), 20);
+        assert_eq!(text.matches('w').count(), 20);
+        assert_eq!(text.lines().count(), 12);
+        assert!(text.lines().nth(1).unwrap().ends_with(" 1- 5"));
+        assert_eq!(
+            text.lines().nth(5).unwrap(),
+            "|   .   .   .   .   . |  21-25"
+        );
+        let unicode = render(&Game::new(), true);
+        assert_eq!(unicode.matches('⛂').count(), 20);
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(
+            parse_command("32-28"),
+            Ok(Command::Move("32-28".to_string()))
+        );
+        assert_eq!(
+            parse_command("fen W:W31:B1"),
+            Ok(Command::Fen(Some("W:W31:B1".to_string())))
+        );
+        assert_eq!(
+            parse_command("pdn save game.pdn"),
+            Ok(Command::PdnSave("game.pdn".to_string()))
+        );
+        assert_eq!(parse_command("undo"), Ok(Command::Undo));
+        assert!(parse_command("castle").is_err());
+    }
+
+    #[test]
+    fn arguments_choose_the_engine_side_and_level() {
+        let args = ["--engine", "white", "--level", "expert", "--ascii"];
+        let options = parse_args(args.iter().map(|arg| arg.to_string()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.engine, Some(Color::White));
+        assert_eq!(options.level, Difficulty::Expert);
+        assert!(!options.unicode);
+        assert!(parse_args(["--level".to_string()].into_iter()).is_err());
+        assert!(parse_args(["--help".to_string()].into_iter())
+            .unwrap()
+            .is_none());
+    }
+}
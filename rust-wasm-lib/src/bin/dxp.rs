@@ -0,0 +1,166 @@
+//! Plays matches against other draughts programs over the DamExchange protocol (DXP).
+//!
+//! One side listens with `dxp --listen 127.0.0.1:27531` and follows the game requests,
+//! the other connects with `dxp --connect 127.0.0.1:27531` and proposes a game.
+
+use rust_wasm_lib::{Color, DxpConnection, DxpGame, DxpPlayer, GameRequest};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use std::{env, process};
+
+const USAGE: &str = "\
+Usage: dxp (--listen <address> | --connect <address>) [OPTIONS]
+
+Options:
+    --listen <address>        wait for a program to connect and play the games it proposes
+    --connect <address>       connect to a listening program and propose a game
+    --name <name>             name sent to the other program (default: rust-wasm-lib)
+    --color <white|black>     color of the listening side when connecting (default: black)
+    --time <minutes>          thinking time of each side for the game (default: 10)
+    --moves <count>           moves the thinking time is for (default: 75)
+    --fen <FEN>               start from a position like W:W31,32:B1,2
+    --move-time <ms>          think this long for every move, ignoring the game time
+    --help                    print this help";
+
+enum Role {
+    Listen(String),
+    Connect(String),
+}
+
+struct Options {
+    role: Role,
+    player: DxpPlayer,
+    request: GameRequest,
+}
+
+//Options to play with, none when only the help was asked for
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut role = None;
+    let mut player = DxpPlayer::new("rust-wasm-lib");
+    let mut request = GameRequest {
+        name: String::new(),
+        follower_color: Color::Black,
+        time_minutes: 10,
+        moves: 75,
+        position: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        let number = |value: String| {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("{value} is not a number"))
+        };
+        match arg.as_str() {
+            "--listen" => role = Some(Role::Listen(value()?)),
+            "--connect" => role = Some(Role::Connect(value()?)),
+            "--name" => player.name = value()?,
+            "--color" => {
+                request.follower_color = match value()?.as_str() {
+                    "white" => Color::White,
+                    "black" => Color::Black,
+                    other => return Err(format!("unknown color {other}")),
+                }
+            }
+            "--time" => request.time_minutes = number(value()?)?,
+            "--moves" => request.moves = number(value()?)?,
+            "--fen" => request.position = Some(value()?),
+            "--move-time" => {
+                player.move_time = Some(Duration::from_millis(number(value()?)?.into()))
+            }
+            "--help" => return Ok(None),
+            other => return Err(format!("unknown option {other}\n\n{USAGE}")),
+        }
+    }
+    let role = role.ok_or(format!("--listen or --connect is needed\n\n{USAGE}"))?;
+    Ok(Some(Options {
+        role,
+        player,
+        request,
+    }))
+}
+
+fn report(game: &mut DxpGame) {
+    println!("Result {}", game.result.notation());
+    print!("{}", game.game.to_pdn());
+}
+
+fn run(options: Options) -> Result<(), String> {
+    match options.role {
+        Role::Listen(address) => {
+            let listener = TcpListener::bind(&address).map_err(|error| error.to_string())?;
+            let address = listener.local_addr().map_err(|error| error.to_string())?;
+            println!("Listening on {address}");
+            let (stream, peer) = listener.accept().map_err(|error| error.to_string())?;
+            println!("Connected to {peer}");
+            let mut connection = DxpConnection::new(stream);
+            while let Some(mut game) = options
+                .player
+                .follow(&mut connection)
+                .map_err(|error| error.to_string())?
+            {
+                report(&mut game);
+            }
+        }
+        Role::Connect(address) => {
+            let stream = TcpStream::connect(&address).map_err(|error| error.to_string())?;
+            println!("Connected to {address}");
+            let mut connection = DxpConnection::new(stream);
+            let mut game = options
+                .player
+                .initiate(&mut connection, &options.request)
+                .map_err(|error| error.to_string())?;
+            report(&mut game);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            process::exit(2);
+        }
+    };
+    if let Err(message) = run(options) {
+        eprintln!("{message}");
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_set_up_the_game_request() {
+        let args = [
+            "--connect",
+            "127.0.0.1:27531",
+            "--color",
+            "white",
+            "--time",
+            "5",
+            "--move-time",
+            "100",
+        ];
+        let options = parse_args(args.iter().map(|arg| arg.to_string()))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(options.role, Role::Connect(address) if address == "127.0.0.1:27531"));
+        assert_eq!(options.request.follower_color, Color::White);
+        assert_eq!(options.request.time_minutes, 5);
+        assert_eq!(options.player.move_time, Some(Duration::from_millis(100)));
+        assert!(parse_args(["--time".to_string()].into_iter()).is_err());
+        assert!(parse_args(std::iter::empty()).is_err());
+        assert!(parse_args(["--help".to_string()].into_iter())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,392 @@
+//! Engine speaking the Hub protocol of Scan over stdin and stdout, for draughts GUIs
+//! and tournament managers.
+//!
+//! The GUI sends `hub`, `init`, `pos`, `level`, `go think`, `ping`, `stop` and `quit`,
+//! the engine answers with `id`, `ready`, `info`, `done` and `pong` lines.
+
+use rust_wasm_lib::{move_squares, Color, Game, GameOutcome, Move, SearchResult};
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const NAME: &str = "rust-wasm-lib";
+//Figures of the starting position, first the side to move, then squares 1 to 50
+const START_POSITION: &str = "Wbbbbbbbbbbbbbbbbbbbbeeeeeeeeeewwwwwwwwwwwwwwwwwwww";
+//Stands in for "no time limit", stop ends such a search
+const NO_TIME_LIMIT: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+//Moves a game is assumed to still last when the GUI doesn't tell
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+//Engine value of a man, scores are reported in men
+const MAN_VALUE: f64 = 100.0;
+
+/// Command name followed by `name=value` pairs, a value may be quoted to contain spaces.
+/// Words without a value are returned with an empty one.
+fn parse_line(line: &str) -> (String, Vec<(String, String)>) {
+    let mut chars = line.trim().chars().peekable();
+    let mut words: Vec<(String, String)> = vec![];
+    while chars.peek().is_some() {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let name: String =
+            std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace() && *c != '=')).collect();
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            if chars.next_if_eq(&'"').is_some() {
+                value = std::iter::from_fn(|| chars.next_if(|c| *c != '"')).collect();
+                chars.next();
+            } else {
+                value = std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect();
+            }
+        }
+        if !name.is_empty() {
+            words.push((name, value));
+        }
+    }
+    if words.is_empty() {
+        return (String::new(), words);
+    }
+    let (command, _) = words.remove(0);
+    (command, words)
+}
+
+/// Game starting from a Hub position like `START_POSITION`.
+fn hub_position(text: &str) -> Result<Game, String> {
+    let mut chars = text.chars();
+    let side_to_move = match chars.next() {
+        Some(side @ ('W' | 'B')) => side,
+        _ => return Err(format!("position \"{text}\" has to start with W or B")),
+    };
+    let squares: Vec<char> = chars.collect();
+    if squares.len() != 50 {
+        return Err(format!("position \"{text}\" has to list 50 squares"));
+    }
+    //Written out as FEN, which also checks that the position is possible
+    let (mut white, mut black) = (vec![], vec![]);
+    for (number, square) in (1..=50).zip(squares) {
+        match square {
+            'w' => white.push(number.to_string()),
+            'W' => white.push(format!("K{number}")),
+            'b' => black.push(number.to_string()),
+            'B' => black.push(format!("K{number}")),
+            'e' => {}
+            other => return Err(format!("unknown square \"{other}\" in position")),
+        }
+    }
+    let fen = format!("{side_to_move}:W{}:B{}", white.join(","), black.join(","));
+    Game::from_fen(&fen).map_err(|error| error.to_string())
+}
+
+/// Move in Hub notation: `32-28`, or for captures the start and end square followed by
+/// every captured square, like `28x19x23`.
+fn hub_move(moves: &[Move]) -> String {
+    let (from, to, captured) = move_squares(moves);
+    let separator = if captured.is_empty() { "-" } else { "x" };
+    [from, to]
+        .iter()
+        .chain(&captured)
+        .map(u32::to_string)
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Legal move of the side to move given in Hub notation.
+fn find_move(game: &mut Game, text: &str) -> Result<Vec<Move>, String> {
+    let mut squares: Vec<u32> = text
+        .split(['-', 'x'])
+        .map(|number| {
+            number
+                .parse()
+                .map_err(|_| format!("invalid move \"{text}\""))
+        })
+        .collect::<Result<_, _>>()?;
+    if squares.len() < 2 {
+        return Err(format!("invalid move \"{text}\""));
+    }
+    squares[2..].sort_unstable();
+    let squares = (squares[0], squares[1], squares[2..].to_vec());
+    game.legal_moves()
+        .into_iter()
+        .find(|moves| move_squares(moves) == squares)
+        .ok_or(format!("illegal move \"{text}\""))
+}
+
+/// Limits of the next search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Level {
+    time_budget: Duration,
+    max_depth: i32,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level {
+            time_budget: Duration::from_secs(1),
+            max_depth: i32::MAX,
+        }
+    }
+}
+
+fn seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or(format!("invalid time \"{value}\""))
+}
+
+/// Level from the arguments of a `level` command, anything not given is reset.
+fn parse_level(args: &[(String, String)]) -> Result<Level, String> {
+    let mut level = Level::default();
+    let (mut time, mut increment, mut moves_to_go) = (None, Duration::ZERO, DEFAULT_MOVES_TO_GO);
+    for (name, value) in args {
+        match name.as_str() {
+            "depth" => {
+                level.max_depth = value
+                    .parse()
+                    .map_err(|_| format!("invalid depth \"{value}\""))?
+            }
+            "move-time" => level.time_budget = seconds(value)?,
+            "time" => time = Some(seconds(value)?),
+            "inc" => increment = seconds(value)?,
+            "moves" => {
+                moves_to_go = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid move count \"{value}\""))?
+                    .max(1)
+            }
+            "infinite" => level.time_budget = NO_TIME_LIMIT,
+            other => return Err(format!("unknown level \"{other}\"")),
+        }
+    }
+    //Share of the remaining time, never more than what is left
+    if let Some(time) = time {
+        level.time_budget = (time / moves_to_go + increment).min(time);
+    }
+    Ok(level)
+}
+
+/// `info` line of a completed depth, the score is in men for the side to move.
+fn info(result: &SearchResult, side_to_move: Color, started: Instant) -> String {
+    let score = match side_to_move {
+        Color::White => result.score(),
+        Color::Black => -result.score(),
+    };
+    let pv: Vec<String> = result.pv().iter().map(|moves| hub_move(moves)).collect();
+    format!(
+        "info depth={} score={:.2} nodes={} time={:.2} nps={} pv=\"{}\"",
+        result.depth(),
+        score as f64 / MAN_VALUE,
+        result.nodes(),
+        started.elapsed().as_secs_f64(),
+        result.nps(),
+        pv.join(" ")
+    )
+}
+
+/// `done` line with the move to play and the expected answer.
+fn done(result: &SearchResult) -> String {
+    let mut line = String::from("done");
+    if !result.best_move().is_empty() {
+        line.push_str(&format!(" move={}", hub_move(result.best_move())));
+    }
+    if let Some(ponder) = result.pv().get(1) {
+        line.push_str(&format!(" ponder={}", hub_move(ponder)));
+    }
+    line
+}
+
+struct Thinking {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Game>,
+}
+
+struct Engine {
+    //None while a search owns the game
+    game: Option<Game>,
+    thinking: Option<Thinking>,
+    level: Level,
+}
+
+impl Engine {
+    fn new() -> Self {
+        Engine {
+            game: Some(Game::new()),
+            thinking: None,
+            level: Level::default(),
+        }
+    }
+
+    //Ends a running search, which answers with its done line
+    fn stop(&mut self) {
+        if let Some(thinking) = self.thinking.take() {
+            thinking.stop.store(true, Ordering::Relaxed);
+            self.game = thinking.handle.join().ok();
+        }
+    }
+
+    fn game(&mut self) -> &mut Game {
+        self.stop();
+        self.game.get_or_insert_with(Game::new)
+    }
+
+    fn think(&mut self, level: Level) {
+        self.stop();
+        let mut game = self.game.take().unwrap_or_default();
+        if game.outcome() != GameOutcome::Ongoing {
+            self.game = Some(game);
+            println!("done");
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let search_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let side_to_move = game.side_to_move();
+            let started = Instant::now();
+            let result = game.think(level.time_budget, level.max_depth, &search_stop, |result| {
+                println!("{}", info(result, side_to_move, started))
+            });
+            println!("{}", done(&result));
+            game
+        });
+        self.thinking = Some(Thinking { stop, handle });
+    }
+
+    /// Answers one line of the GUI, false once the engine should quit.
+    fn run(&mut self, line: &str) -> Result<bool, String> {
+        let (command, args) = parse_line(line);
+        let arg = |name: &str| {
+            args.iter()
+                .find(|(arg, _)| arg == name)
+                .map(|(_, value)| value.as_str())
+        };
+        match command.as_str() {
+            "" => {}
+            "hub" => {
+                println!(
+                    "id name={NAME} version={} author={}",
+                    env!("CARGO_PKG_VERSION"),
+                    env!("CARGO_PKG_AUTHORS")
+                );
+                println!("wait");
+            }
+            "init" => {
+                self.game();
+                println!("ready");
+            }
+            "ping" => println!("pong"),
+            "new-game" => *self.game() = Game::new(),
+            "pos" => {
+                let mut game = hub_position(arg("pos").unwrap_or(START_POSITION))?;
+                for text in arg("moves").unwrap_or_default().split_whitespace() {
+                    let moves = find_move(&mut game, text)?;
+                    game.make_move(moves).map_err(|error| error.to_string())?;
+                }
+                *self.game() = game;
+            }
+            "level" => self.level = parse_level(&args)?,
+            "go" => match args.first().map(|(word, _)| word.as_str()) {
+                Some("think") => self.think(self.level),
+                Some("analyze") => self.think(Level {
+                    time_budget: NO_TIME_LIMIT,
+                    max_depth: i32::MAX,
+                }),
+                _ => return Err("go needs think or analyze".to_string()),
+            },
+            "stop" => self.stop(),
+            "quit" => {
+                self.stop();
+                return Ok(false);
+            }
+            other => return Err(format!("unknown command \"{other}\"")),
+        }
+        Ok(true)
+    }
+}
+
+fn main() {
+    //Lines are read on their own thread, so stop and ping are seen during a search
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let mut engine = Engine::new();
+    for line in lines {
+        match engine.run(&line) {
+            Ok(true) => {}
+            Ok(false) => return,
+            //Quotes would end the value early
+            Err(message) => println!("error message=\"{}\"", message.replace('"', "'")),
+        }
+    }
+    engine.stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_split_into_quoted_arguments() {
+        let (command, args) = parse_line("pos pos=Wbe moves=\"32-28 19-23\"  ");
+        assert_eq!(command, "pos");
+        assert_eq!(
+            args,
+            [
+                ("pos".to_string(), "Wbe".to_string()),
+                ("moves".to_string(), "32-28 19-23".to_string())
+            ]
+        );
+        let (command, args) = parse_line("go think");
+        assert_eq!((command.as_str(), args[0].0.as_str()), ("go", "think"));
+    }
+
+    fn position(side_to_move: char, figures: &[(u32, char)]) -> String {
+        let mut squares = vec!['e'; 50];
+        for (number, figure) in figures {
+            squares[*number as usize - 1] = *figure;
+        }
+        std::iter::once(side_to_move).chain(squares).collect()
+    }
+
+    #[test]
+    fn positions_are_read_square_by_square() {
+        let mut game = hub_position(START_POSITION).unwrap();
+        assert_eq!(game.figure_map().len(), 40);
+        assert_eq!(game.side_to_move(), Color::White);
+        assert!(find_move(&mut game, "32-28").is_ok());
+        let game = hub_position(&position('B', &[(45, 'W'), (6, 'b')])).unwrap();
+        assert_eq!(game.to_fen(), "B:WK45:B6");
+        assert!(hub_position("Wbbb").is_err());
+        assert!(hub_position(&position('W', &[(1, 'x')])).is_err());
+    }
+
+    #[test]
+    fn captures_list_every_captured_square() {
+        let mut game = hub_position(&position('W', &[(32, 'w'), (28, 'b'), (19, 'b')])).unwrap();
+        let moves = find_move(&mut game, "32x14x28x19").unwrap();
+        assert_eq!(hub_move(&moves), "32x14x19x28");
+        assert_eq!(
+            find_move(&mut game, "32-27").unwrap_err(),
+            "illegal move \"32-27\""
+        );
+        assert!(find_move(&mut game, "32").is_err());
+    }
+
+    #[test]
+    fn level_shares_the_remaining_time() {
+        let args = parse_line("level time=60 inc=1 moves=20").1;
+        assert_eq!(
+            parse_level(&args).unwrap().time_budget,
+            Duration::from_secs(4)
+        );
+        let args = parse_line("level depth=6").1;
+        assert_eq!(parse_level(&args).unwrap().max_depth, 6);
+        assert!(parse_level(&parse_line("level time=x").1).is_err());
+    }
+}
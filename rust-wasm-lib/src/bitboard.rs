@@ -0,0 +1,436 @@
+use crate::{Color, IFigure};
+use std::collections::HashMap;
+
+//The 50 playable squares are stored row by row with a ghost bit after every two rows,
+//so a diagonal step is the same shift everywhere and steps off the board hit a ghost
+const GHOST_BITS: u64 = 1 << 10 | 1 << 21 | 1 << 32 | 1 << 43;
+pub(crate) const BOARD_MASK: u64 = ((1 << 54) - 1) & !GHOST_BITS;
+
+//Bit offsets of a diagonal step, white men move up
+const UP_LEFT: i32 = -6;
+const UP_RIGHT: i32 = -5;
+const DOWN_LEFT: i32 = 5;
+const DOWN_RIGHT: i32 = 6;
+const DIRECTIONS: [i32; 4] = [UP_LEFT, UP_RIGHT, DOWN_LEFT, DOWN_RIGHT];
+
+/// Bit of a playable square of the 10x10 grid.
+pub(crate) fn square_bit(sqare_no: i32) -> Option<u32> {
+    let (row, col) = (sqare_no / 10, sqare_no % 10);
+    if !(0..100).contains(&sqare_no) || (row + col) % 2 == 0 {
+        return None;
+    }
+    Some((row * 5 + col / 2 + row / 2) as u32)
+}
+
+/// Square of the 10x10 grid stored at a bit.
+pub(crate) fn bit_square(bit: u32) -> i32 {
+    let playable_no = bit as i32 - bit as i32 / 11;
+    let (row, col) = (playable_no / 5, playable_no % 5 * 2);
+    row * 10 + col + (row + 1) % 2
+}
+
+fn shift(bitboard: u64, direction: i32) -> u64 {
+    let shifted = if direction > 0 {
+        bitboard << direction
+    } else {
+        bitboard >> -direction
+    };
+    shifted & BOARD_MASK
+}
+
+fn step(bit: u32, direction: i32) -> Option<u32> {
+    let target = shift(1 << bit, direction);
+    (target != 0).then(|| target.trailing_zeros())
+}
+
+pub(crate) fn bits(mut bitboard: u64) -> impl Iterator<Item = u32> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let bit = bitboard.trailing_zeros();
+        bitboard &= bitboard - 1;
+        Some(bit)
+    })
+}
+
+fn promotion_row(color: Color) -> u64 {
+    match color {
+        Color::White => 0b11111,
+        Color::Black => 0b11111 << 49,
+    }
+}
+
+fn forward_directions(color: Color) -> [i32; 2] {
+    match color {
+        Color::White => [UP_LEFT, UP_RIGHT],
+        Color::Black => [DOWN_LEFT, DOWN_RIGHT],
+    }
+}
+
+/// Single step of a move, the figure jumped on the way for a capture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Hop {
+    pub(crate) to: u32,
+    pub(crate) captured: Option<u32>,
+}
+
+/// Whole move of one figure, a capture sequence is stored by the figures it takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BitMove {
+    pub(crate) from: u8,
+    pub(crate) to: u8,
+    pub(crate) captured: u64,
+    pub(crate) promoted: bool,
+}
+
+impl BitMove {
+    pub(crate) fn is_capture(&self) -> bool {
+        self.captured != 0
+    }
+}
+
+/// Figures as bit masks of the playable squares.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub(crate) struct Position {
+    pub(crate) white: u64,
+    pub(crate) black: u64,
+    pub(crate) kings: u64,
+}
+
+impl Position {
+    pub(crate) fn from_figure_map(figure_map: &HashMap<i32, IFigure>) -> Position {
+        let mut position = Position::default();
+        for (figure_no, figure) in figure_map {
+            position.put(*figure_no, figure);
+        }
+        position
+    }
+
+    /// Places a figure on a square, squares off the board are ignored.
+    pub(crate) fn put(&mut self, sqare_no: i32, figure: &IFigure) {
+        let Some(bit) = square_bit(sqare_no) else {
+            return;
+        };
+        match figure.color.as_str() {
+            "white" => self.white |= 1 << bit,
+            "black" => self.black |= 1 << bit,
+            _ => return,
+        }
+        if figure.kind == "king" {
+            self.kings |= 1 << bit;
+        }
+    }
+
+    pub(crate) fn remove(&mut self, sqare_no: i32) {
+        if let Some(bit) = square_bit(sqare_no) {
+            let mask = !(1 << bit);
+            self.white &= mask;
+            self.black &= mask;
+            self.kings &= mask;
+        }
+    }
+
+    pub(crate) fn figures(&self, color: Color) -> u64 {
+        match color {
+            Color::White => self.white,
+            Color::Black => self.black,
+        }
+    }
+
+    pub(crate) fn empty(&self) -> u64 {
+        BOARD_MASK & !(self.white | self.black)
+    }
+
+    pub(crate) fn color_at(&self, bit: u32) -> Option<Color> {
+        if self.white & 1 << bit != 0 {
+            Some(Color::White)
+        } else if self.black & 1 << bit != 0 {
+            Some(Color::Black)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn is_king(&self, bit: u32) -> bool {
+        self.kings & 1 << bit != 0
+    }
+
+    /// Hops of the figure on `from` when it starts at `origin` and has already taken `captured`.
+    fn for_each_capture(&self, origin: u32, from: u32, captured: u64, mut add: impl FnMut(Hop)) {
+        let Some(color) = self.color_at(origin) else {
+            return;
+        };
+        //Captured figures stay on the board until the sequence is done, so they block
+        let empty = (self.empty() | 1 << origin) & !(1 << from);
+        let capturable = self.figures(color.opponent()) & !captured;
+        let is_king = self.is_king(origin);
+        for direction in DIRECTIONS {
+            let mut next = step(from, direction);
+            if is_king {
+                while let Some(bit) = next.filter(|bit| empty & 1 << bit != 0) {
+                    next = step(bit, direction);
+                }
+            }
+            let Some(jumped) = next.filter(|bit| capturable & 1 << bit != 0) else {
+                continue;
+            };
+            let mut landing = step(jumped, direction);
+            while let Some(to) = landing.filter(|bit| empty & 1 << bit != 0) {
+                add(Hop {
+                    to,
+                    captured: Some(jumped),
+                });
+                //Flying king can land on any empty square beyond the captured figure
+                landing = if is_king { step(to, direction) } else { None };
+            }
+        }
+    }
+
+    fn for_each_quiet_hop(&self, from: u32, mut add: impl FnMut(Hop)) {
+        let Some(color) = self.color_at(from) else {
+            return;
+        };
+        let empty = self.empty();
+        if self.is_king(from) {
+            for direction in DIRECTIONS {
+                let mut next = step(from, direction);
+                while let Some(to) = next.filter(|bit| empty & 1 << bit != 0) {
+                    add(Hop { to, captured: None });
+                    next = step(to, direction);
+                }
+            }
+        } else {
+            for direction in forward_directions(color) {
+                if let Some(to) = step(from, direction).filter(|bit| empty & 1 << bit != 0) {
+                    add(Hop { to, captured: None });
+                }
+            }
+        }
+    }
+
+    /// Number of single hops of the figure on `from`, captures aren't forced.
+    pub(crate) fn mobility(&self, from: u32) -> u32 {
+        let mut hops_no = 0;
+        self.for_each_quiet_hop(from, |_| hops_no += 1);
+        self.for_each_capture(from, from, 0, |_| hops_no += 1);
+        hops_no
+    }
+
+    /// Single hops of the figure on `from`, captures aren't forced.
+    pub(crate) fn figure_moves(&self, from: u32) -> Vec<BitMove> {
+        let mut hops: Vec<Hop> = vec![];
+        self.for_each_quiet_hop(from, |hop| hops.push(hop));
+        self.for_each_capture(from, from, 0, |hop| hops.push(hop));
+        hops.into_iter()
+            .map(|hop| {
+                let captured = hop.captured.map_or(0, |bit| 1 << bit);
+                BitMove {
+                    from: from as u8,
+                    to: hop.to as u8,
+                    captured,
+                    promoted: self.promotes(from, hop.to, captured),
+                }
+            })
+            .collect()
+    }
+
+    fn promotes(&self, from: u32, to: u32, captured: u64) -> bool {
+        let Some(color) = self.color_at(from) else {
+            return false;
+        };
+        if self.is_king(from) || promotion_row(color) & 1 << to == 0 {
+            return false;
+        }
+        //Capture ending on the last row promotes only if the capture can't be continued
+        let mut can_continue = false;
+        if captured != 0 {
+            self.for_each_capture(from, to, captured, |_| can_continue = true);
+        }
+        !can_continue
+    }
+
+    pub(crate) fn has_moves(&self, color: Color) -> bool {
+        bits(self.figures(color)).any(|from| self.mobility(from) > 0)
+    }
+
+    /// Capture sequences of `color` taking the most figures, each listed once however
+    /// the figures are jumped.
+    pub(crate) fn captures(&self, color: Color, moves: &mut Vec<BitMove>) {
+        moves.clear();
+        for from in bits(self.figures(color)) {
+            self.extend_capture(from, from, 0, moves);
+        }
+    }
+
+    fn extend_capture(&self, origin: u32, from: u32, captured: u64, moves: &mut Vec<BitMove>) {
+        let mut extended = false;
+        self.for_each_capture(origin, from, captured, |hop| {
+            extended = true;
+            let captured = captured | hop.captured.map_or(0, |bit| 1 << bit);
+            self.extend_capture(origin, hop.to, captured, moves);
+        });
+        if extended || captured == 0 {
+            return;
+        }
+        let longest = moves.first().map_or(0, |mov| mov.captured.count_ones());
+        if captured.count_ones() > longest {
+            moves.clear();
+        }
+        let mov = BitMove {
+            from: origin as u8,
+            to: from as u8,
+            captured,
+            promoted: self.promotes(origin, from, captured),
+        };
+        if captured.count_ones() >= longest && !moves.contains(&mov) {
+            moves.push(mov);
+        }
+    }
+
+    /// Moves of `color` without captures, ordered by the starting square.
+    pub(crate) fn quiet_moves(&self, color: Color, moves: &mut Vec<BitMove>) {
+        moves.clear();
+        let empty = self.empty();
+        let men = self.figures(color) & !self.kings;
+        //All men step in the same direction at once
+        for direction in forward_directions(color) {
+            for to in bits(shift(men, direction) & empty) {
+                moves.push(BitMove {
+                    from: (to as i32 - direction) as u8,
+                    to: to as u8,
+                    captured: 0,
+                    promoted: promotion_row(color) & 1 << to != 0,
+                });
+            }
+        }
+        for from in bits(self.figures(color) & self.kings) {
+            self.for_each_quiet_hop(from, |hop| {
+                moves.push(BitMove {
+                    from: from as u8,
+                    to: hop.to as u8,
+                    captured: 0,
+                    promoted: false,
+                })
+            });
+        }
+        moves.sort_by_key(|mov| mov.from);
+    }
+
+    /// Legal moves of `color`, captures are mandatory.
+    pub(crate) fn legal_moves(&self, color: Color, moves: &mut Vec<BitMove>) {
+        self.captures(color, moves);
+        if moves.is_empty() {
+            self.quiet_moves(color, moves);
+        }
+    }
+
+    /// Hops of a move in the order the figures are taken, for showing it hop by hop.
+    pub(crate) fn hops(&self, mov: BitMove) -> Vec<Hop> {
+        let mut hops: Vec<Hop> = vec![];
+        if mov.is_capture() {
+            self.find_hops(mov.from.into(), mov.from.into(), 0, mov, &mut hops);
+        } else {
+            hops.push(Hop {
+                to: mov.to.into(),
+                captured: None,
+            });
+        }
+        hops
+    }
+
+    fn find_hops(
+        &self,
+        origin: u32,
+        from: u32,
+        captured: u64,
+        mov: BitMove,
+        hops: &mut Vec<Hop>,
+    ) -> bool {
+        if captured == mov.captured {
+            return from == u32::from(mov.to);
+        }
+        let mut next_hops: Vec<Hop> = vec![];
+        self.for_each_capture(origin, from, captured, |hop| {
+            if hop.captured.is_some_and(|bit| mov.captured & 1 << bit != 0) {
+                next_hops.push(hop);
+            }
+        });
+        for hop in next_hops {
+            hops.push(hop);
+            let next_captured = captured | hop.captured.map_or(0, |bit| 1 << bit);
+            if self.find_hops(origin, hop.to, next_captured, mov, hops) {
+                return true;
+            }
+            hops.pop();
+        }
+        false
+    }
+
+    /// Plays a legal move of the figure standing on `mov.from`.
+    pub(crate) fn make(&mut self, mov: BitMove) {
+        let (from, to) = (1u64 << mov.from, 1u64 << mov.to);
+        let is_king = self.kings & from != 0;
+        if self.white & from != 0 {
+            self.white = self.white & !from | to;
+            self.black &= !mov.captured;
+        } else {
+            self.black = self.black & !from | to;
+            self.white &= !mov.captured;
+        }
+        self.kings &= !(from | mov.captured);
+        if is_king || mov.promoted {
+            self.kings |= to;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initial_figure_map;
+
+    #[test]
+    fn bits_map_to_playable_squares() {
+        let squares: Vec<i32> = bits(BOARD_MASK).map(bit_square).collect();
+        assert_eq!(squares.len(), 50);
+        for (bit, sqare_no) in bits(BOARD_MASK).zip(&squares) {
+            assert_eq!(square_bit(*sqare_no), Some(bit));
+        }
+        //Bits follow the grid order
+        assert!(squares.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(squares[..6], [1, 3, 5, 7, 9, 10]);
+        assert_eq!(square_bit(0), None);
+    }
+
+    #[test]
+    fn diagonal_steps_match_the_grid() {
+        for bit in bits(BOARD_MASK) {
+            let sqare_no = bit_square(bit);
+            //Grid steps of up left, up right, down left and down right with the column change
+            let grid_steps = [(-11, -1), (-9, 1), (9, -1), (11, 1)];
+            for (direction, (grid_step, col_step)) in DIRECTIONS.into_iter().zip(grid_steps) {
+                let col = sqare_no % 10 + col_step;
+                let expected = if (0..10).contains(&col) {
+                    square_bit(sqare_no + grid_step)
+                } else {
+                    None
+                };
+                assert_eq!(step(bit, direction), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn start_position_quiet_moves() {
+        let position = Position::from_figure_map(&initial_figure_map());
+        assert_eq!(position.white.count_ones(), 20);
+        assert_eq!(position.black.count_ones(), 20);
+        let mut moves: Vec<BitMove> = vec![];
+        position.captures(Color::White, &mut moves);
+        assert!(moves.is_empty());
+        position.legal_moves(Color::White, &mut moves);
+        assert_eq!(moves.len(), 9);
+        assert!(moves.windows(2).all(|pair| pair[0].from <= pair[1].from));
+    }
+}
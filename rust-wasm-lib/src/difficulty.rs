@@ -0,0 +1,238 @@
+use crate::bitboard::BitMove;
+use crate::validation::figure_map_from_js;
+use crate::{Board, Color, IFigure, Move, Search, TRANSPOSITION_TABLE};
+use instant::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Strength presets of the computer opponent.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const DifficultySettings: &'static str = r#"
+export interface DifficultySettings {
+    depth: number,
+    time_ms: number,
+    eval_noise: number,
+    suboptimal_move_chance: number,
+}
+"#;
+
+/// How deep and how long the engine thinks, and how much it is allowed to err.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+pub struct DifficultySettings {
+    depth: i32,
+    time_ms: u32,
+    //Random value up to this much is added to the score of every root move
+    eval_noise: i32,
+    //Probability of playing a move other than the best one
+    suboptimal_move_chance: f64,
+}
+
+impl Difficulty {
+    pub fn settings(&self) -> DifficultySettings {
+        let (depth, time_ms, eval_noise, suboptimal_move_chance) = match self {
+            Difficulty::Beginner => (1, 100, 200, 0.3),
+            Difficulty::Easy => (2, 250, 100, 0.15),
+            Difficulty::Medium => (4, 500, 30, 0.05),
+            Difficulty::Hard => (6, 1000, 0, 0.0),
+            Difficulty::Expert => (10, 3000, 0, 0.0),
+        };
+        DifficultySettings {
+            depth,
+            time_ms,
+            eval_noise,
+            suboptimal_move_chance,
+        }
+    }
+}
+
+/// xorshift64*, good enough for making the engine play worse.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        //State can't be zero
+        Rng(seed | 1)
+    }
+
+    pub(crate) fn from_time() -> Self {
+        Rng::new(instant::now().to_bits())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        let fraction = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        fraction < probability
+    }
+}
+
+impl Board<'_> {
+    /// Picks the move the engine plays at the given strength.
+    pub(crate) fn choose_move(
+        &mut self,
+        search: &mut Search,
+        settings: &DifficultySettings,
+        rng: &mut Rng,
+        color: Color,
+    ) -> Vec<Move> {
+        let time_budget = Duration::from_millis(settings.time_ms.into());
+        if settings.eval_noise == 0 && settings.suboptimal_move_chance == 0.0 {
+            return self
+                .iterative_deepening(search, time_budget, settings.depth, color)
+                .best_move()
+                .to_vec();
+        }
+
+        let (_, scored_moves) = self.score_root_moves(search, time_budget, settings.depth, color);
+        let perspective = match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        let noise_range = 2 * settings.eval_noise as u64 + 1;
+        let mut noisy_moves: Vec<(i32, BitMove)> = scored_moves
+            .into_iter()
+            .map(|(value, mov)| {
+                let noise = rng.below(noise_range) as i32 - settings.eval_noise;
+                (value * perspective + noise, mov)
+            })
+            .collect();
+        noisy_moves.sort_by_key(|(value, _)| -value);
+
+        let mut index = 0;
+        if noisy_moves.len() > 1 && rng.chance(settings.suboptimal_move_chance) {
+            index = 1 + rng.below(noisy_moves.len() as u64 - 1) as usize;
+        }
+        noisy_moves
+            .into_iter()
+            .nth(index)
+            .map(|(_, mov)| self.to_moves(mov))
+            .unwrap_or_default()
+    }
+}
+
+#[wasm_bindgen]
+pub fn difficulty_settings(difficulty: Difficulty) -> Result<JsValue, JsError> {
+    Ok(serde_wasm_bindgen::to_value(&difficulty.settings())?)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const get_best_move_with_difficulty: &'static str = r#"
+export function get_best_move_with_difficulty(color: Color, figure_map: Map<number, IFigure>, difficulty: Difficulty): Move[];
+"#;
+
+#[wasm_bindgen(skip_typescript)]
+pub fn get_best_move_with_difficulty(
+    color: Color,
+    figure_map: JsValue,
+    difficulty: Difficulty,
+) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+    let mut board: Board = Board::new(&mut figure_map);
+    let mov = TRANSPOSITION_TABLE.with(|tt| {
+        let mut tt = tt.borrow_mut();
+        let mut search = Search::new(&mut tt);
+        board.choose_move(
+            &mut search,
+            &difficulty.settings(),
+            &mut Rng::from_time(),
+            color,
+        )
+    });
+    Ok(serde_wasm_bindgen::to_value(&mov)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_figure_map, TranspositionTable};
+
+    const LEVELS: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    #[test]
+    fn harder_levels_search_deeper_and_err_less() {
+        for levels in LEVELS.windows(2) {
+            let (easier, harder) = (levels[0].settings(), levels[1].settings());
+            assert!(easier.depth < harder.depth);
+            assert!(easier.time_ms < harder.time_ms);
+            assert!(easier.eval_noise >= harder.eval_noise);
+            assert!(easier.suboptimal_move_chance >= harder.suboptimal_move_chance);
+        }
+    }
+
+    #[test]
+    fn every_level_plays_a_legal_move() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        let legal_moves = board.legal_moves(&Color::White);
+        let mut rng = Rng::new(7);
+        for level in LEVELS.iter().take(3) {
+            let mut tt = TranspositionTable::default();
+            let mut search = Search::new(&mut tt);
+            let mov = board.choose_move(&mut search, &level.settings(), &mut rng, Color::White);
+            assert!(legal_moves.iter().any(|legal_mov| {
+                legal_mov[0].moved_figure_no == mov[0].moved_figure_no
+                    && legal_mov[0].square_no == mov[0].square_no
+            }));
+        }
+    }
+
+    #[test]
+    fn suboptimal_move_still_respects_mandatory_capture() {
+        //Capturing 34 is the only legal move, so there is nothing worse to pick
+        let figure = |color: &str| IFigure {
+            color: color.to_string(),
+            kind: "man".to_string(),
+        };
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (45, figure("white")),
+            (34, figure("black")),
+            (81, figure("white")),
+            (3, figure("black")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let settings = DifficultySettings {
+            suboptimal_move_chance: 1.0,
+            ..Difficulty::Easy.settings()
+        };
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let mov = board.choose_move(&mut search, &settings, &mut Rng::new(1), Color::White);
+        assert_eq!(mov[0].captured_figure_no, Some(34));
+    }
+
+    #[test]
+    fn rng_is_deterministic_for_a_seed() {
+        let (mut first, mut second) = (Rng::new(42), Rng::new(42));
+        for _ in 0..10 {
+            assert_eq!(first.next_u64(), second.next_u64());
+        }
+        assert!(!Rng::new(42).chance(0.0));
+        assert!(Rng::new(42).chance(1.0));
+    }
+}
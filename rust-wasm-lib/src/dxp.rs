@@ -0,0 +1,755 @@
+use crate::notation::{grid_square, move_squares};
+use crate::{from_fen, to_fen, Color, FenError, Game, GameOutcome, PdnResult};
+use core::fmt;
+use instant::{Duration, Instant};
+use std::io::{self, Read, Write};
+
+//Version of the DamExchange protocol sent with a game request
+const VERSION: &str = "01";
+//Engine names are padded with spaces to this many characters
+const NAME_LENGTH: usize = 32;
+//Moves still to play when the game request leaves it open
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Reason why a DXP message couldn't be read or the match couldn't go on.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DxpError {
+    Connection(String),
+    Closed,
+    UnknownMessage(String),
+    InvalidMessage(String),
+    InvalidPosition(FenError),
+    IllegalMove(String),
+    Refused(AcceptCode),
+    Unexpected(String),
+}
+
+impl DxpError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DxpError::Connection(_) => "CONNECTION",
+            DxpError::Closed => "CLOSED",
+            DxpError::UnknownMessage(_) => "UNKNOWN_MESSAGE",
+            DxpError::InvalidMessage(_) => "INVALID_MESSAGE",
+            DxpError::InvalidPosition(_) => "INVALID_POSITION",
+            DxpError::IllegalMove(_) => "ILLEGAL_MOVE",
+            DxpError::Refused(_) => "REFUSED",
+            DxpError::Unexpected(_) => "UNEXPECTED",
+        }
+    }
+}
+
+impl fmt::Display for DxpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DxpError::Connection(message) => write!(f, "connection failed: {message}"),
+            DxpError::Closed => write!(f, "connection was closed"),
+            DxpError::UnknownMessage(text) => write!(f, "\"{text}\" is not a DXP message"),
+            DxpError::InvalidMessage(text) => write!(f, "DXP message \"{text}\" is malformed"),
+            DxpError::InvalidPosition(error) => write!(f, "starting position is invalid: {error}"),
+            DxpError::IllegalMove(text) => write!(f, "opponent played illegal move \"{text}\""),
+            DxpError::Refused(code) => write!(f, "game request was refused ({code:?})"),
+            DxpError::Unexpected(text) => write!(f, "DXP message \"{text}\" was not expected now"),
+        }
+    }
+}
+
+impl std::error::Error for DxpError {}
+
+impl From<io::Error> for DxpError {
+    fn from(error: io::Error) -> Self {
+        DxpError::Connection(error.to_string())
+    }
+}
+
+impl From<FenError> for DxpError {
+    fn from(error: FenError) -> Self {
+        DxpError::InvalidPosition(error)
+    }
+}
+
+/// Answer of the follower to a game request.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AcceptCode {
+    Accepted,
+    UnknownInitiator,
+    SettingsRefused,
+    NotReady,
+}
+
+/// Answer to a request to take moves back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackCode {
+    Accepted,
+    NotSupported,
+    Refused,
+}
+
+/// Why a game ended, seen from the side sending the message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EndReason {
+    Unknown,
+    SenderLoses,
+    Draw,
+    SenderWins,
+}
+
+/// Game the initiator proposes. `position` is a FEN, `None` for the initial position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRequest {
+    pub name: String,
+    pub follower_color: Color,
+    pub time_minutes: u32,
+    pub moves: u32,
+    pub position: Option<String>,
+}
+
+/// Message of the DamExchange protocol, sent terminated by a `\0`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DxpMessage {
+    GameReq(GameRequest),
+    GameAcc {
+        name: String,
+        code: AcceptCode,
+    },
+    Move {
+        seconds: u32,
+        from: u32,
+        to: u32,
+        captured: Vec<u32>,
+    },
+    BackReq {
+        move_no: u32,
+        side_to_move: Color,
+    },
+    BackAcc(BackCode),
+    GameEnd {
+        reason: EndReason,
+        last_game: bool,
+    },
+    Chat(String),
+}
+
+//Digit of a code enum, in the order of its variants
+fn code_digit(index: usize) -> char {
+    char::from_digit(index as u32, 10).unwrap_or('0')
+}
+
+fn color_char(color: Color) -> char {
+    match color {
+        Color::White => 'W',
+        Color::Black => 'Z',
+    }
+}
+
+fn padded_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '?' })
+        .take(NAME_LENGTH)
+        .collect();
+    format!("{name:<NAME_LENGTH$}")
+}
+
+//Color to move and every square from 1 to 50 as e, w, z, W or Z
+fn position_text(fen: &str) -> Result<String, DxpError> {
+    let (figure_map, side_to_move) = from_fen(fen)?;
+    let squares = (1..=50).map(|number| {
+        let figure = grid_square(number).and_then(|sqare_no| figure_map.get(&sqare_no));
+        match figure.map(|figure| (figure.color(), figure.is_king())) {
+            None => 'e',
+            Some((Color::White, false)) => 'w',
+            Some((Color::White, true)) => 'W',
+            Some((Color::Black, false)) => 'z',
+            Some((Color::Black, true)) => 'Z',
+        }
+    });
+    Ok(std::iter::once(color_char(side_to_move))
+        .chain(squares)
+        .collect())
+}
+
+fn position_fen(text: &str) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let side_to_move = match chars.first()? {
+        'W' => "W",
+        'Z' => "B",
+        _ => return None,
+    };
+    if chars.len() != 51 {
+        return None;
+    }
+    let (mut white, mut black) = (vec![], vec![]);
+    for (number, square) in (1..=50).zip(&chars[1..]) {
+        match square {
+            'w' => white.push(number.to_string()),
+            'W' => white.push(format!("K{number}")),
+            'z' => black.push(number.to_string()),
+            'Z' => black.push(format!("K{number}")),
+            'e' => {}
+            _ => return None,
+        }
+    }
+    let fen = format!("{side_to_move}:W{}:B{}", white.join(","), black.join(","));
+    let (figure_map, side_to_move) = from_fen(&fen).ok()?;
+    Some(to_fen(&figure_map, side_to_move))
+}
+
+impl DxpMessage {
+    /// Text of the message without the terminating `\0`.
+    pub fn encode(&self) -> Result<String, DxpError> {
+        let text = match self {
+            DxpMessage::GameReq(request) => {
+                let position = match &request.position {
+                    Some(fen) => format!("B{}", position_text(fen)?),
+                    None => "A".to_string(),
+                };
+                format!(
+                    "R{VERSION}{}{}{:03}{:03}{position}",
+                    padded_name(&request.name),
+                    color_char(request.follower_color),
+                    request.time_minutes.min(999),
+                    request.moves.min(999)
+                )
+            }
+            DxpMessage::GameAcc { name, code } => {
+                format!("A{}{}", padded_name(name), code_digit(*code as usize))
+            }
+            DxpMessage::Move {
+                seconds,
+                from,
+                to,
+                captured,
+            } => {
+                let squares: String = captured
+                    .iter()
+                    .map(|number| format!("{number:02}"))
+                    .collect();
+                format!(
+                    "M{:04}{from:02}{to:02}{:02}{squares}",
+                    (*seconds).min(9999),
+                    captured.len()
+                )
+            }
+            DxpMessage::BackReq {
+                move_no,
+                side_to_move,
+            } => format!("B{:03}{}", (*move_no).min(999), color_char(*side_to_move)),
+            DxpMessage::BackAcc(code) => format!("K{}", code_digit(*code as usize)),
+            DxpMessage::GameEnd { reason, last_game } => {
+                format!("E{}{}", code_digit(*reason as usize), u8::from(*last_game))
+            }
+            DxpMessage::Chat(text) => format!("C{text}"),
+        };
+        Ok(text)
+    }
+
+    /// Message from its text without the terminating `\0`.
+    pub fn decode(text: &str) -> Result<DxpMessage, DxpError> {
+        let invalid = || DxpError::InvalidMessage(text.to_string());
+        //Field of the given width starting at `start`
+        let field = |start: usize, width: usize| text.get(start..start + width).ok_or_else(invalid);
+        let number =
+            |start: usize, width: usize| field(start, width)?.parse::<u32>().map_err(|_| invalid());
+        let color = |start: usize| match field(start, 1)? {
+            "W" => Ok(Color::White),
+            "Z" => Ok(Color::Black),
+            _ => Err(invalid()),
+        };
+        let code = |start: usize, codes_no: usize| {
+            let code = number(start, 1)? as usize;
+            if code < codes_no {
+                Ok(code)
+            } else {
+                Err(invalid())
+            }
+        };
+        let message = match text.get(..1) {
+            Some("R") => {
+                if field(1, 2)? != VERSION {
+                    return Err(invalid());
+                }
+                let position = match field(42, 1)? {
+                    "A" => None,
+                    "B" => Some(
+                        position_fen(text.get(43..).ok_or_else(invalid)?).ok_or_else(invalid)?,
+                    ),
+                    _ => return Err(invalid()),
+                };
+                DxpMessage::GameReq(GameRequest {
+                    name: field(3, NAME_LENGTH)?.trim_end().to_string(),
+                    follower_color: color(35)?,
+                    time_minutes: number(36, 3)?,
+                    moves: number(39, 3)?,
+                    position,
+                })
+            }
+            Some("A") => DxpMessage::GameAcc {
+                name: field(1, NAME_LENGTH)?.trim_end().to_string(),
+                code: [
+                    AcceptCode::Accepted,
+                    AcceptCode::UnknownInitiator,
+                    AcceptCode::SettingsRefused,
+                    AcceptCode::NotReady,
+                ][code(33, 4)?],
+            },
+            Some("M") => {
+                let captured_no = number(9, 2)? as usize;
+                if text.len() != 11 + 2 * captured_no {
+                    return Err(invalid());
+                }
+                DxpMessage::Move {
+                    seconds: number(1, 4)?,
+                    from: number(5, 2)?,
+                    to: number(7, 2)?,
+                    captured: (0..captured_no)
+                        .map(|index| number(11 + 2 * index, 2))
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+            Some("B") => DxpMessage::BackReq {
+                move_no: number(1, 3)?,
+                side_to_move: color(4)?,
+            },
+            Some("K") => DxpMessage::BackAcc(
+                [
+                    BackCode::Accepted,
+                    BackCode::NotSupported,
+                    BackCode::Refused,
+                ][code(1, 3)?],
+            ),
+            Some("E") => DxpMessage::GameEnd {
+                reason: [
+                    EndReason::Unknown,
+                    EndReason::SenderLoses,
+                    EndReason::Draw,
+                    EndReason::SenderWins,
+                ][code(1, 4)?],
+                last_game: code(2, 2)? == 1,
+            },
+            Some("C") => DxpMessage::Chat(text[1..].to_string()),
+            _ => return Err(DxpError::UnknownMessage(text.to_string())),
+        };
+        Ok(message)
+    }
+}
+
+/// Stream carrying DXP messages, usually a `TcpStream`.
+pub struct DxpConnection<S> {
+    stream: S,
+    //Bytes read past the end of the last message
+    received: Vec<u8>,
+}
+
+impl<S: Read + Write> DxpConnection<S> {
+    pub fn new(stream: S) -> Self {
+        DxpConnection {
+            stream,
+            received: vec![],
+        }
+    }
+
+    pub fn send(&mut self, message: &DxpMessage) -> Result<(), DxpError> {
+        let mut bytes = message.encode()?.into_bytes();
+        bytes.push(0);
+        self.stream.write_all(&bytes)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// Next message, `DxpError::Closed` once the other side hung up.
+    pub fn receive(&mut self) -> Result<DxpMessage, DxpError> {
+        loop {
+            if let Some(end) = self.received.iter().position(|byte| *byte == 0) {
+                let bytes: Vec<u8> = self.received.drain(..=end).collect();
+                return DxpMessage::decode(&String::from_utf8_lossy(&bytes[..end]));
+            }
+            let mut buffer = [0; 256];
+            match self.stream.read(&mut buffer)? {
+                0 => return Err(DxpError::Closed),
+                read => self.received.extend_from_slice(&buffer[..read]),
+            }
+        }
+    }
+}
+
+/// Finished game of a DXP match.
+pub struct DxpGame {
+    pub game: Game,
+    pub result: PdnResult,
+}
+
+/// Engine taking part in DXP matches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DxpPlayer {
+    pub name: String,
+    //Time for every move instead of sharing out the time of the game request
+    pub move_time: Option<Duration>,
+}
+
+//Result from the end reason and which side sent it
+fn end_result(reason: EndReason, sender: Color) -> PdnResult {
+    match (reason, sender) {
+        (EndReason::Draw, _) => PdnResult::Draw,
+        (EndReason::SenderWins, Color::White) | (EndReason::SenderLoses, Color::Black) => {
+            PdnResult::WhiteWins
+        }
+        (EndReason::SenderWins, Color::Black) | (EndReason::SenderLoses, Color::White) => {
+            PdnResult::BlackWins
+        }
+        (EndReason::Unknown, _) => PdnResult::Unknown,
+    }
+}
+
+impl DxpPlayer {
+    pub fn new(name: &str) -> Self {
+        DxpPlayer {
+            name: name.to_string(),
+            move_time: None,
+        }
+    }
+
+    /// Proposes a game to the follower and plays it. The initiator never asks for another game.
+    pub fn initiate<S: Read + Write>(
+        &self,
+        connection: &mut DxpConnection<S>,
+        request: &GameRequest,
+    ) -> Result<DxpGame, DxpError> {
+        let game = match &request.position {
+            Some(fen) => Game::from_fen(fen)?,
+            None => Game::new(),
+        };
+        let request = GameRequest {
+            name: self.name.clone(),
+            ..request.clone()
+        };
+        connection.send(&DxpMessage::GameReq(request.clone()))?;
+        match connection.receive()? {
+            DxpMessage::GameAcc {
+                code: AcceptCode::Accepted,
+                ..
+            } => {}
+            DxpMessage::GameAcc { code, .. } => return Err(DxpError::Refused(code)),
+            other => return Err(DxpError::Unexpected(other.encode()?)),
+        }
+        let color = request.follower_color.opponent();
+        self.play(connection, game, color, &request, true)
+    }
+
+    /// Waits for a game request, accepts it and plays the game.
+    /// `None` once the initiator closed the connection instead of proposing another game.
+    pub fn follow<S: Read + Write>(
+        &self,
+        connection: &mut DxpConnection<S>,
+    ) -> Result<Option<DxpGame>, DxpError> {
+        let request = loop {
+            match connection.receive() {
+                Ok(DxpMessage::GameReq(request)) => break request,
+                Ok(DxpMessage::Chat(_)) => continue,
+                Ok(other) => return Err(DxpError::Unexpected(other.encode()?)),
+                Err(DxpError::Closed) => return Ok(None),
+                Err(error) => return Err(error),
+            }
+        };
+        let game = match &request.position {
+            Some(fen) => Game::from_fen(fen)?,
+            None => Game::new(),
+        };
+        connection.send(&DxpMessage::GameAcc {
+            name: self.name.clone(),
+            code: AcceptCode::Accepted,
+        })?;
+        self.play(connection, game, request.follower_color, &request, false)
+            .map(Some)
+    }
+
+    //Time for the next move, the game time shared out over the moves still to play
+    fn time_budget(
+        &self,
+        request: &GameRequest,
+        time_used: Duration,
+        moves_played: u32,
+    ) -> Duration {
+        if let Some(move_time) = self.move_time {
+            return move_time;
+        }
+        let time_left =
+            Duration::from_secs(u64::from(request.time_minutes) * 60).saturating_sub(time_used);
+        let moves_to_go = match request.moves.checked_sub(moves_played) {
+            Some(moves) if moves > 0 => moves,
+            _ => DEFAULT_MOVES_TO_GO,
+        };
+        time_left / moves_to_go
+    }
+
+    //Plays until either side sends the game end, the side to move sends it once the game is over
+    fn play<S: Read + Write>(
+        &self,
+        connection: &mut DxpConnection<S>,
+        mut game: Game,
+        color: Color,
+        request: &GameRequest,
+        last_game: bool,
+    ) -> Result<DxpGame, DxpError> {
+        let (mut time_used, mut moves_played) = (Duration::ZERO, 0);
+        let start_color = game.side_to_move();
+        loop {
+            if game.side_to_move() == color {
+                let outcome = game.outcome();
+                if outcome != GameOutcome::Ongoing {
+                    let reason = match outcome.winner() {
+                        Some(winner) if winner == color => EndReason::SenderWins,
+                        Some(_) => EndReason::SenderLoses,
+                        None => EndReason::Draw,
+                    };
+                    connection.send(&DxpMessage::GameEnd { reason, last_game })?;
+                    self.wait_for_game_end(connection)?;
+                    return Ok(DxpGame {
+                        game,
+                        result: PdnResult::from(outcome),
+                    });
+                }
+                let started = Instant::now();
+                let budget = self.time_budget(request, time_used, moves_played);
+                let moves = game.best_move_timed(budget).best_move().to_vec();
+                let (from, to, captured) = move_squares(&moves);
+                game.make_move(moves)
+                    .map_err(|error| DxpError::IllegalMove(error.to_string()))?;
+                time_used += started.elapsed();
+                moves_played += 1;
+                connection.send(&DxpMessage::Move {
+                    seconds: started.elapsed().as_secs() as u32,
+                    from,
+                    to,
+                    captured,
+                })?;
+                continue;
+            }
+            match connection.receive()? {
+                DxpMessage::Move {
+                    from,
+                    to,
+                    mut captured,
+                    ..
+                } => {
+                    captured.sort_unstable();
+                    let moves = game
+                        .legal_moves()
+                        .into_iter()
+                        .find(|moves| move_squares(moves) == (from, to, captured.clone()))
+                        .ok_or_else(|| {
+                            DxpError::IllegalMove(format!("{from}-{to} capturing {captured:?}"))
+                        })?;
+                    game.make_move(moves)
+                        .map_err(|error| DxpError::IllegalMove(error.to_string()))?;
+                }
+                DxpMessage::GameEnd { reason, .. } => {
+                    let result = end_result(reason, color.opponent());
+                    //Answered with the same result from this side
+                    let reason = match reason {
+                        EndReason::SenderWins => EndReason::SenderLoses,
+                        EndReason::SenderLoses => EndReason::SenderWins,
+                        other => other,
+                    };
+                    connection.send(&DxpMessage::GameEnd { reason, last_game })?;
+                    return Ok(DxpGame { game, result });
+                }
+                DxpMessage::BackReq {
+                    move_no,
+                    side_to_move,
+                } => {
+                    //Plies from the start to the position with `side_to_move` to play move `move_no`
+                    let plies = (i64::from(move_no) - 1) * 2
+                        + i64::from(side_to_move == Color::Black)
+                        - i64::from(start_color == Color::Black);
+                    let code = if (0..=game.history().len() as i64).contains(&plies) {
+                        while game.history().len() as i64 > plies {
+                            game.undo();
+                        }
+                        BackCode::Accepted
+                    } else {
+                        BackCode::Refused
+                    };
+                    connection.send(&DxpMessage::BackAcc(code))?;
+                }
+                DxpMessage::Chat(_) => {}
+                other => return Err(DxpError::Unexpected(other.encode()?)),
+            }
+        }
+    }
+
+    fn wait_for_game_end<S: Read + Write>(
+        &self,
+        connection: &mut DxpConnection<S>,
+    ) -> Result<(), DxpError> {
+        loop {
+            match connection.receive()? {
+                DxpMessage::GameEnd { .. } => return Ok(()),
+                DxpMessage::Chat(_) => {}
+                other => return Err(DxpError::Unexpected(other.encode()?)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    #[test]
+    fn messages_round_trip() {
+        let request = DxpMessage::GameReq(GameRequest {
+            name: "Scan".to_string(),
+            follower_color: Color::Black,
+            time_minutes: 10,
+            moves: 75,
+            position: Some("B:W32,K50:B19,28".to_string()),
+        });
+        let text = request.encode().unwrap();
+        assert!(text.starts_with("R01Scan "));
+        assert_eq!(&text[35..44], "Z010075BZ");
+        assert_eq!(text.len(), 94);
+        assert_eq!(DxpMessage::decode(&text).unwrap(), request);
+
+        let capture = DxpMessage::Move {
+            seconds: 3,
+            from: 32,
+            to: 14,
+            captured: vec![19, 28],
+        };
+        assert_eq!(capture.encode().unwrap(), "M00033214021928");
+        assert_eq!(DxpMessage::decode("M00033214021928").unwrap(), capture);
+        for message in [
+            DxpMessage::GameAcc {
+                name: "rust-wasm-lib".to_string(),
+                code: AcceptCode::NotReady,
+            },
+            DxpMessage::BackReq {
+                move_no: 12,
+                side_to_move: Color::White,
+            },
+            DxpMessage::BackAcc(BackCode::Refused),
+            DxpMessage::GameEnd {
+                reason: EndReason::Draw,
+                last_game: true,
+            },
+            DxpMessage::Chat("good luck".to_string()),
+        ] {
+            assert_eq!(
+                DxpMessage::decode(&message.encode().unwrap()).unwrap(),
+                message
+            );
+        }
+        assert_eq!(
+            DxpMessage::BackReq {
+                move_no: 12,
+                side_to_move: Color::Black
+            }
+            .encode()
+            .unwrap(),
+            "B012Z"
+        );
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        assert_eq!(
+            DxpMessage::decode("X").unwrap_err(),
+            DxpError::UnknownMessage("X".to_string())
+        );
+        assert_eq!(
+            DxpMessage::decode("M0003321402").unwrap_err(),
+            DxpError::InvalidMessage("M0003321402".to_string())
+        );
+        assert!(DxpMessage::decode("K7").is_err());
+        assert!(DxpMessage::decode("B01").is_err());
+        let request = DxpMessage::GameReq(GameRequest {
+            name: "Scan".to_string(),
+            follower_color: Color::White,
+            time_minutes: 1,
+            moves: 50,
+            position: Some("W:W3:B1".to_string()),
+        });
+        assert!(matches!(
+            request.encode().unwrap_err(),
+            DxpError::InvalidPosition(FenError::ManOnLastRow(3))
+        ));
+    }
+
+    #[test]
+    fn follower_plays_takes_back_and_ends_the_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let follower = thread::spawn(move || {
+            let mut player = DxpPlayer::new("follower");
+            player.move_time = Some(Duration::from_millis(50));
+            let mut connection = DxpConnection::new(listener.accept().unwrap().0);
+            let game = player.follow(&mut connection).unwrap().unwrap();
+            let next = player.follow(&mut connection).unwrap();
+            (game.result, game.game.history().len(), next.is_none())
+        });
+
+        let mut connection = DxpConnection::new(TcpStream::connect(address).unwrap());
+        connection
+            .send(&DxpMessage::GameReq(GameRequest {
+                name: "initiator".to_string(),
+                follower_color: Color::White,
+                time_minutes: 1,
+                moves: 50,
+                position: Some("W:W32,50:B1,19,28".to_string()),
+            }))
+            .unwrap();
+        assert_eq!(
+            connection.receive().unwrap(),
+            DxpMessage::GameAcc {
+                name: "follower".to_string(),
+                code: AcceptCode::Accepted
+            }
+        );
+        //Only move is the double capture
+        let capture = |message: DxpMessage| match message {
+            DxpMessage::Move {
+                from, to, captured, ..
+            } => (from, to, captured),
+            other => panic!("{other:?} is not a move"),
+        };
+        assert_eq!(
+            capture(connection.receive().unwrap()),
+            (32, 14, vec![19, 28])
+        );
+        connection
+            .send(&DxpMessage::Chat("well played".to_string()))
+            .unwrap();
+        connection
+            .send(&DxpMessage::BackReq {
+                move_no: 1,
+                side_to_move: Color::White,
+            })
+            .unwrap();
+        assert_eq!(
+            connection.receive().unwrap(),
+            DxpMessage::BackAcc(BackCode::Accepted)
+        );
+        assert_eq!(
+            capture(connection.receive().unwrap()),
+            (32, 14, vec![19, 28])
+        );
+        connection
+            .send(&DxpMessage::GameEnd {
+                reason: EndReason::SenderLoses,
+                last_game: true,
+            })
+            .unwrap();
+        assert_eq!(
+            connection.receive().unwrap(),
+            DxpMessage::GameEnd {
+                reason: EndReason::SenderWins,
+                last_game: false
+            }
+        );
+        drop(connection);
+        assert_eq!(follower.join().unwrap(), (PdnResult::WhiteWins, 1, true));
+    }
+}
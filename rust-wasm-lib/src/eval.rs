@@ -0,0 +1,178 @@
+use crate::bitboard::{bit_square, bits};
+use crate::Board;
+
+/// Weights of the evaluation terms, a man is worth 100.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvalWeights {
+    pub man: i32,
+    pub king: i32,
+    //Per row a man has advanced from its own back rank
+    pub advancement: i32,
+    //Per figure on the central squares
+    pub center: i32,
+    //Per man still guarding its own back rank
+    pub back_rank: i32,
+    //Per available move
+    pub mobility: i32,
+    //Per figure which can't move at all, usually negative
+    pub trapped: i32,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights {
+            man: 100,
+            king: 300,
+            advancement: 3,
+            center: 4,
+            back_rank: 6,
+            mobility: 2,
+            trapped: -10,
+        }
+    }
+}
+
+fn is_center(sqare_no: i32) -> bool {
+    (3..=6).contains(&(sqare_no / 10)) && (2..=7).contains(&(sqare_no % 10))
+}
+
+impl Board<'_> {
+    /// Score of the position from white's point of view.
+    pub(crate) fn evaluate(&self, weights: &EvalWeights) -> i32 {
+        let mut rating = 0;
+        let position = &self.position;
+        for bit in bits(position.white | position.black) {
+            let figure_no = bit_square(bit);
+            let row = figure_no / 10;
+            let (sign, rows_advanced, back_row) = if position.white & 1 << bit != 0 {
+                (1, 9 - row, 9)
+            } else {
+                (-1, row, 0)
+            };
+            let mut figure_rating = 0;
+            if !position.is_king(bit) {
+                figure_rating += weights.man + weights.advancement * rows_advanced;
+                if row == back_row {
+                    figure_rating += weights.back_rank;
+                }
+            } else {
+                figure_rating += weights.king;
+            }
+            if is_center(figure_no) {
+                figure_rating += weights.center;
+            }
+            let moves_no = position.mobility(bit) as i32;
+            figure_rating += weights.mobility * moves_no;
+            if moves_no == 0 {
+                figure_rating += weights.trapped;
+            }
+            rating += sign * figure_rating;
+        }
+        rating
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_figure_map, IFigure};
+    use std::collections::HashMap;
+
+    fn figure(color: &str, kind: &str) -> IFigure {
+        IFigure {
+            color: color.to_string(),
+            kind: kind.to_string(),
+        }
+    }
+
+    fn rating(figures: &[(i32, &str, &str)]) -> i32 {
+        let mut figure_map: HashMap<i32, IFigure> = figures
+            .iter()
+            .map(|(figure_no, color, kind)| (*figure_no, figure(color, kind)))
+            .collect();
+        Board::new(&mut figure_map).evaluate(&EvalWeights::default())
+    }
+
+    #[test]
+    fn starting_position_is_balanced() {
+        let mut figure_map = initial_figure_map();
+        assert_eq!(
+            Board::new(&mut figure_map).evaluate(&EvalWeights::default()),
+            0
+        );
+    }
+
+    #[test]
+    fn material_outweighs_position() {
+        let king = rating(&[(54, "white", "king"), (3, "black", "man")]);
+        let man = rating(&[(54, "white", "man"), (3, "black", "man")]);
+        assert!(king > man);
+        assert!(
+            rating(&[
+                (90, "white", "man"),
+                (92, "white", "man"),
+                (45, "black", "man")
+            ]) > 0
+        );
+    }
+
+    #[test]
+    fn advanced_man_is_preferred() {
+        let advanced = rating(&[(36, "white", "man"), (1, "black", "man")]);
+        let behind = rating(&[(76, "white", "man"), (1, "black", "man")]);
+        assert!(advanced > behind);
+    }
+
+    #[test]
+    fn center_is_preferred_over_edge() {
+        let center = rating(&[(54, "white", "man"), (1, "black", "man")]);
+        let edge = rating(&[(50, "white", "man"), (1, "black", "man")]);
+        assert!(center > edge);
+    }
+
+    #[test]
+    fn guarding_back_rank_is_preferred() {
+        //Man on 92 guards the back rank, on 83 it is a row further and has one more move
+        let guarded = rating(&[
+            (92, "white", "man"),
+            (81, "white", "man"),
+            (3, "black", "man"),
+        ]);
+        let unguarded = rating(&[
+            (81, "white", "man"),
+            (83, "white", "man"),
+            (3, "black", "man"),
+        ]);
+        let weights = EvalWeights::default();
+        assert_eq!(
+            guarded - unguarded,
+            weights.back_rank - weights.advancement - weights.mobility
+        );
+        assert!(guarded > unguarded);
+    }
+
+    #[test]
+    fn mobile_king_is_preferred_over_cornered_one() {
+        let mobile = rating(&[(45, "white", "king"), (1, "black", "man")]);
+        let cornered = rating(&[(90, "white", "king"), (1, "black", "man")]);
+        assert!(mobile > cornered);
+    }
+
+    #[test]
+    fn trapped_man_is_penalized() {
+        //White man on 61 is blocked by its own men, the one on 69 is free
+        let trapped = rating(&[
+            (61, "white", "man"),
+            (50, "white", "man"),
+            (52, "white", "man"),
+            (3, "black", "man"),
+        ]);
+        let free = rating(&[
+            (69, "white", "man"),
+            (50, "white", "man"),
+            (52, "white", "man"),
+            (3, "black", "man"),
+        ]);
+        assert!(free > trapped);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::notation::{grid_square, square_number};
+use crate::validation::figure_map_from_js;
+use crate::{Color, Game, IFigure};
+use core::fmt;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+//Figures each side starts with, no position can have more
+const MAX_FIGURES: usize = 20;
+
+#[wasm_bindgen(typescript_custom_section)]
+const FenError: &'static str = r#"
+export interface FenError extends Error {
+    code: "MISSING_SIDE_TO_MOVE" | "INVALID_SIDE_TO_MOVE" | "INVALID_COLOR" | "INVALID_SQUARE"
+        | "SQUARE_TAKEN_TWICE" | "MAN_ON_LAST_ROW" | "TOO_MANY_FIGURES",
+}
+"#;
+
+/// Reason why a FEN string doesn't describe a position.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FenError {
+    MissingSideToMove,
+    InvalidSideToMove(String),
+    InvalidColor(String),
+    InvalidSquare(String),
+    SquareTakenTwice(u32),
+    ManOnLastRow(u32),
+    TooManyFigures(Color),
+}
+
+impl FenError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            FenError::MissingSideToMove => "MISSING_SIDE_TO_MOVE",
+            FenError::InvalidSideToMove(_) => "INVALID_SIDE_TO_MOVE",
+            FenError::InvalidColor(_) => "INVALID_COLOR",
+            FenError::InvalidSquare(_) => "INVALID_SQUARE",
+            FenError::SquareTakenTwice(_) => "SQUARE_TAKEN_TWICE",
+            FenError::ManOnLastRow(_) => "MAN_ON_LAST_ROW",
+            FenError::TooManyFigures(_) => "TOO_MANY_FIGURES",
+        }
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::MissingSideToMove => write!(f, "side to move is missing"),
+            FenError::InvalidSideToMove(text) => {
+                write!(f, "side to move has to be W or B, not \"{text}\"")
+            }
+            FenError::InvalidColor(text) => {
+                write!(f, "figure list has to start with W or B, not \"{text}\"")
+            }
+            FenError::InvalidSquare(text) => {
+                write!(f, "\"{text}\" is not a square number between 1 and 50")
+            }
+            FenError::SquareTakenTwice(number) => {
+                write!(f, "square {number} is given more than one figure")
+            }
+            FenError::ManOnLastRow(number) => {
+                write!(f, "man on square {number} should have become a king")
+            }
+            FenError::TooManyFigures(color) => {
+                write!(f, "{color:?} has more than {MAX_FIGURES} figures")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl From<FenError> for JsValue {
+    fn from(error: FenError) -> Self {
+        let js_error: JsValue = JsError::new(&error.to_string()).into();
+        let _ = js_sys::Reflect::set(&js_error, &"code".into(), &error.code().into());
+        js_error
+    }
+}
+
+/// Position in the FEN notation of PDN, like `W:W31,32,K45:B1,2`.
+pub fn to_fen(figure_map: &HashMap<i32, IFigure>, side_to_move: Color) -> String {
+    let figures = |color: Color| {
+        let mut figures: Vec<(u32, bool)> = figure_map
+            .iter()
+            .filter(|(_, figure)| figure.color == color.as_str())
+            .filter_map(|(sqare_no, figure)| {
+                square_number(*sqare_no).map(|number| (number, figure.kind == "king"))
+            })
+            .collect();
+        figures.sort_unstable();
+        figures
+            .iter()
+            .map(|(number, king)| format!("{}{number}", if *king { "K" } else { "" }))
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+    let side = match side_to_move {
+        Color::White => "W",
+        Color::Black => "B",
+    };
+    format!(
+        "{side}:W{}:B{}",
+        figures(Color::White),
+        figures(Color::Black)
+    )
+}
+
+/// Figures and side to move of a FEN position. Squares may be given as ranges like `31-35`,
+/// a trailing `.` and surrounding quotes are ignored.
+pub fn from_fen(text: &str) -> Result<(HashMap<i32, IFigure>, Color), FenError> {
+    let text = text.trim().trim_matches('"').trim_end_matches('.');
+    let mut fields = text.split(':');
+    let side_to_move = match fields.next().map(str::trim) {
+        None | Some("") => return Err(FenError::MissingSideToMove),
+        Some("W") => Color::White,
+        Some("B") => Color::Black,
+        Some(side) => return Err(FenError::InvalidSideToMove(side.to_string())),
+    };
+    let mut figure_map: HashMap<i32, IFigure> = HashMap::new();
+    for field in fields {
+        let field = field.trim();
+        let (color, squares) = match (field.strip_prefix('W'), field.strip_prefix('B')) {
+            (Some(squares), _) => (Color::White, squares),
+            (_, Some(squares)) => (Color::Black, squares),
+            _ => return Err(FenError::InvalidColor(field.to_string())),
+        };
+        for square in squares.split(',').map(str::trim) {
+            if square.is_empty() {
+                continue;
+            }
+            let (kind, numbers) = match square.strip_prefix('K') {
+                Some(numbers) => ("king", numbers),
+                None => ("man", square),
+            };
+            for number in square_range(numbers).ok_or(FenError::InvalidSquare(square.into()))? {
+                let sqare_no =
+                    grid_square(number).ok_or(FenError::InvalidSquare(square.to_string()))?;
+                let last_row = match color {
+                    Color::White => 1..=5,
+                    Color::Black => 46..=50,
+                };
+                if kind == "man" && last_row.contains(&number) {
+                    return Err(FenError::ManOnLastRow(number));
+                }
+                let figure = IFigure {
+                    color: color.as_str().to_string(),
+                    kind: kind.to_string(),
+                };
+                if figure_map.insert(sqare_no, figure).is_some() {
+                    return Err(FenError::SquareTakenTwice(number));
+                }
+            }
+        }
+    }
+    for color in [Color::White, Color::Black] {
+        let count = figure_map
+            .values()
+            .filter(|figure| figure.color == color.as_str())
+            .count();
+        if count > MAX_FIGURES {
+            return Err(FenError::TooManyFigures(color));
+        }
+    }
+    Ok((figure_map, side_to_move))
+}
+
+//Single square number or an inclusive range of them
+fn square_range(text: &str) -> Option<std::ops::RangeInclusive<u32>> {
+    let (first, last) = text.split_once('-').unwrap_or((text, text));
+    let (first, last) = (first.trim().parse().ok()?, last.trim().parse().ok()?);
+    (first <= last).then_some(first..=last)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const to_fen: &'static str = r#"
+export function to_fen(color: Color, figure_map: Map<number, IFigure>): string;
+"#;
+
+#[wasm_bindgen(js_name = to_fen, skip_typescript)]
+pub fn to_fen_js(color: Color, figure_map: JsValue) -> Result<String, JsError> {
+    let figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+    Ok(to_fen(&figure_map, color))
+}
+
+#[wasm_bindgen]
+impl Game {
+    /// Game starting from a FEN position.
+    pub fn from_fen(text: &str) -> Result<Game, FenError> {
+        let (figure_map, side_to_move) = from_fen(text)?;
+        Ok(Game::from_figure_map(figure_map, side_to_move))
+    }
+
+    /// Current position in FEN.
+    pub fn to_fen(&self) -> String {
+        to_fen(self.figure_map(), self.side_to_move())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initial_figure_map;
+
+    #[test]
+    fn initial_position() {
+        let figure_map = initial_figure_map();
+        let fen = to_fen(&figure_map, Color::White);
+        let numbers = |range: std::ops::RangeInclusive<u32>| {
+            range
+                .map(|number| number.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        assert_eq!(fen, format!("W:W{}:B{}", numbers(31..=50), numbers(1..=20)));
+        let (read, side_to_move) = from_fen("W:W31-50:B1-20").unwrap();
+        assert_eq!(side_to_move, Color::White);
+        assert_eq!(to_fen(&read, side_to_move), fen);
+    }
+
+    #[test]
+    fn kings_and_side_to_move_round_trip() {
+        let fen = "B:W6,K10,32:BK1,19,K45";
+        let (figure_map, side_to_move) = from_fen(fen).unwrap();
+        assert_eq!(side_to_move, Color::Black);
+        assert_eq!(figure_map.len(), 6);
+        let king = &figure_map[&grid_square(45).unwrap()];
+        assert_eq!((king.color.as_str(), king.kind.as_str()), ("black", "king"));
+        assert_eq!(figure_map[&grid_square(6).unwrap()].kind, "man");
+        assert_eq!(to_fen(&figure_map, side_to_move), fen);
+        //Quotes, spaces, a final dot and sections in any order are accepted
+        let (read, _) = from_fen("\"B:B K1, 19,K45:W6,K10,32.\"").unwrap();
+        assert_eq!(to_fen(&read, Color::Black), fen);
+    }
+
+    #[test]
+    fn malformed_and_impossible_positions_are_rejected() {
+        assert_eq!(from_fen("").unwrap_err(), FenError::MissingSideToMove);
+        assert_eq!(
+            from_fen("X:W31:B1").unwrap_err(),
+            FenError::InvalidSideToMove("X".to_string())
+        );
+        assert_eq!(
+            from_fen("W:R31:B1").unwrap_err(),
+            FenError::InvalidColor("R31".to_string())
+        );
+        assert_eq!(
+            from_fen("W:W51:B1").unwrap_err(),
+            FenError::InvalidSquare("51".to_string())
+        );
+        assert_eq!(
+            from_fen("W:W31,a:B1").unwrap_err(),
+            FenError::InvalidSquare("a".to_string())
+        );
+        assert_eq!(
+            from_fen("W:W31:B31").unwrap_err(),
+            FenError::SquareTakenTwice(31)
+        );
+        assert_eq!(from_fen("W:W3:B31").unwrap_err(), FenError::ManOnLastRow(3));
+        assert!(from_fen("W:WK3:BK48").is_ok());
+        assert_eq!(
+            from_fen("W:W21-41:B1").unwrap_err(),
+            FenError::TooManyFigures(Color::White)
+        );
+        assert_eq!(
+            FenError::ManOnLastRow(3).to_string(),
+            "man on square 3 should have become a king"
+        );
+    }
+
+    #[test]
+    fn game_starts_from_fen() {
+        let mut game = Game::from_fen("B:W28,K50:B19").unwrap();
+        assert_eq!(game.side_to_move(), Color::Black);
+        assert_eq!(game.legal_moves().len(), 2);
+        assert_eq!(game.to_fen(), "B:W28,K50:B19");
+    }
+}
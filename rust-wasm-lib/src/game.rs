@@ -0,0 +1,337 @@
+use crate::difficulty::Rng;
+use crate::validation::figure_map_from_js;
+use crate::{
+    initial_figure_map, outcome::PositionHistory, AnalysisLine, Board, CaptureSequence, Color,
+    Difficulty, GameOutcome, IFigure, Move, MoveError, NotationError, Search, SearchResult,
+    TranspositionTable, MAX_SEARCH_DEPTH,
+};
+use instant::Duration;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const Game: &'static str = r#"
+export class Game {
+    free(): void;
+    constructor();
+    static from_figure_map(figure_map: Map<number, IFigure>, side_to_move: Color): Game;
+    readonly side_to_move: Color;
+    figure_map(): Map<number, IFigure>;
+    legal_moves(): Move[][];
+    capture_sequences(): CaptureSequence[];
+    /** @throws {NotationError} */
+    parse_move(text: string): Move[];
+    /** @throws {MoveError} */
+    make_move(moves: Move[]): void;
+    undo(): boolean;
+    outcome(): GameOutcome;
+    best_move_timed(time_ms: number): SearchResult;
+    analyze(lines_no: number, time_ms: number): AnalysisLine[];
+    engine_move(difficulty: Difficulty): Move[];
+    set_hash_size(megabytes: number): void;
+    history(): Move[][];
+    /** @throws {PdnError} */
+    static from_pdn(text: string): Game;
+    to_pdn(): string;
+    /** @throws {FenError} */
+    static from_fen(text: string): Game;
+    to_fen(): string;
+}
+"#;
+
+/// Game state kept on the Rust side: position, side to move and played moves.
+#[wasm_bindgen(skip_typescript)]
+pub struct Game {
+    figure_map: HashMap<i32, IFigure>,
+    side_to_move: Color,
+    history: Vec<Vec<Move>>,
+    //Position after each played move, for the draw rules
+    positions: PositionHistory,
+    tt: TranspositionTable,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Game {
+        Game::from_figure_map(initial_figure_map(), Color::White)
+    }
+
+    #[wasm_bindgen(js_name = from_figure_map)]
+    pub fn from_figure_map_js(figure_map: JsValue, side_to_move: Color) -> Result<Game, JsError> {
+        let figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+        Ok(Game::from_figure_map(figure_map, side_to_move))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    #[wasm_bindgen(js_name = figure_map)]
+    pub fn figure_map_js(&self) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.figure_map)?)
+    }
+
+    #[wasm_bindgen(js_name = legal_moves)]
+    pub fn legal_moves_js(&mut self) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.legal_moves())?)
+    }
+
+    #[wasm_bindgen(js_name = capture_sequences)]
+    pub fn capture_sequences_js(&mut self) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.capture_sequences())?)
+    }
+
+    #[wasm_bindgen(js_name = make_move)]
+    pub fn make_move_js(&mut self, moves: JsValue) -> Result<(), JsValue> {
+        let moves: Vec<Move> = serde_wasm_bindgen::from_value(moves)?;
+        self.make_move(moves)?;
+        Ok(())
+    }
+
+    /// Takes back the last move, returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(moves) => {
+                Board::new(&mut self.figure_map).unmake_moves(&moves);
+                self.positions.pop();
+                self.side_to_move = self.side_to_move.opponent();
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[wasm_bindgen(js_name = parse_move)]
+    pub fn parse_move_js(&mut self, text: &str) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.parse_move(text)?)?)
+    }
+
+    #[wasm_bindgen(js_name = outcome)]
+    pub fn outcome_js(&mut self) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.outcome())?)
+    }
+
+    #[wasm_bindgen(js_name = best_move_timed)]
+    pub fn best_move_timed_js(&mut self, time_ms: u32) -> Result<JsValue, JsError> {
+        let result = self.best_move_timed(Duration::from_millis(time_ms.into()));
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+
+    #[wasm_bindgen(js_name = analyze)]
+    pub fn analyze_js(&mut self, lines_no: usize, time_ms: u32) -> Result<JsValue, JsError> {
+        let lines = self.analyze(lines_no, Duration::from_millis(time_ms.into()));
+        Ok(serde_wasm_bindgen::to_value(&lines)?)
+    }
+
+    #[wasm_bindgen(js_name = engine_move)]
+    pub fn engine_move_js(&mut self, difficulty: Difficulty) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.engine_move(difficulty))?)
+    }
+
+    /// Sets memory used by the engine's transposition table, clears the table.
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        self.tt = TranspositionTable::with_memory(megabytes);
+    }
+
+    #[wasm_bindgen(js_name = history)]
+    pub fn history_js(&self) -> Result<JsValue, JsError> {
+        Ok(serde_wasm_bindgen::to_value(&self.history)?)
+    }
+}
+
+impl Game {
+    pub fn from_figure_map(mut figure_map: HashMap<i32, IFigure>, side_to_move: Color) -> Game {
+        let board = Board::new(&mut figure_map);
+        let positions = PositionHistory::new(board.position_key(side_to_move), &board.position);
+        Game {
+            figure_map,
+            side_to_move,
+            history: vec![],
+            positions,
+            tt: TranspositionTable::default(),
+        }
+    }
+
+    pub fn figure_map(&self) -> &HashMap<i32, IFigure> {
+        &self.figure_map
+    }
+
+    pub fn history(&self) -> &[Vec<Move>] {
+        &self.history
+    }
+
+    /// Every legal move for the side to move, capture sequences are complete.
+    pub fn legal_moves(&mut self) -> Vec<Vec<Move>> {
+        let side_to_move = self.side_to_move;
+        Board::new(&mut self.figure_map).legal_moves(&side_to_move)
+    }
+
+    /// Mandatory capture sequences for the side to move, empty if there is no capture.
+    pub fn capture_sequences(&mut self) -> Vec<CaptureSequence> {
+        let side_to_move = self.side_to_move;
+        Board::new(&mut self.figure_map)
+            .capture_sequences(&side_to_move)
+            .into_iter()
+            .map(CaptureSequence::from)
+            .collect()
+    }
+
+    /// Legal move of the side to move written in standard notation, like `32-28`.
+    pub fn parse_move(&mut self, text: &str) -> Result<Vec<Move>, NotationError> {
+        let side_to_move = self.side_to_move;
+        Board::new(&mut self.figure_map).notation_move(side_to_move, text)
+    }
+
+    /// Validates and plays the move, position is left untouched on error.
+    pub fn make_move(&mut self, moves: Vec<Move>) -> Result<(), MoveError> {
+        let side_to_move = self.side_to_move;
+        let mut board = Board::new(&mut self.figure_map);
+        let moves = board.apply_move(&side_to_move, &moves)?;
+        self.positions.push(
+            board.position_key(side_to_move.opponent()),
+            moves[0].moved_figure.kind == "king",
+            moves.iter().any(|mov| mov.captured_figure_no.is_some()),
+            &board.position,
+        );
+        self.history.push(moves);
+        self.side_to_move = self.side_to_move.opponent();
+        Ok(())
+    }
+
+    /// Whether the side to move can still play, and who won if not.
+    pub fn outcome(&mut self) -> GameOutcome {
+        Board::new(&mut self.figure_map).outcome(self.side_to_move, &self.positions)
+    }
+
+    /// Move of the computer opponent playing at the given strength.
+    pub fn engine_move(&mut self, difficulty: Difficulty) -> Vec<Move> {
+        let side_to_move = self.side_to_move;
+        let mut board = Board::new(&mut self.figure_map);
+        let mut search = Search::new(&mut self.tt);
+        search.positions = self.positions.clone();
+        board.choose_move(
+            &mut search,
+            &difficulty.settings(),
+            &mut Rng::from_time(),
+            side_to_move,
+        )
+    }
+
+    /// Best `lines_no` moves for the side to move, best first, each with its own variation.
+    pub fn analyze(&mut self, lines_no: usize, time_budget: Duration) -> Vec<AnalysisLine> {
+        let side_to_move = self.side_to_move;
+        let mut board = Board::new(&mut self.figure_map);
+        let mut search = Search::new(&mut self.tt);
+        search.positions = self.positions.clone();
+        board.multi_pv(&mut search, time_budget, lines_no, side_to_move)
+    }
+
+    /// Best move found within the time budget, with the depth it was searched to.
+    pub fn best_move_timed(&mut self, time_budget: Duration) -> SearchResult {
+        let side_to_move = self.side_to_move;
+        let mut board = Board::new(&mut self.figure_map);
+        let mut search = Search::new(&mut self.tt);
+        search.positions = self.positions.clone();
+        board.iterative_deepening(&mut search, time_budget, MAX_SEARCH_DEPTH, side_to_move)
+    }
+
+    /// Like `best_move_timed`, also ending at `max_depth` or once `stop` is set after the
+    /// first iteration. `report` gets the result of every completed depth.
+    pub fn think(
+        &mut self,
+        time_budget: Duration,
+        max_depth: i32,
+        stop: &AtomicBool,
+        report: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let side_to_move = self.side_to_move;
+        let mut board = Board::new(&mut self.figure_map);
+        let mut search = Search::new(&mut self.tt);
+        search.positions = self.positions.clone();
+        search.stop_signal = Some(stop);
+        search.on_iteration = Some(Box::new(report));
+        let max_depth = max_depth.clamp(1, MAX_SEARCH_DEPTH);
+        board.iterative_deepening(&mut search, time_budget, max_depth, side_to_move)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_game_starts_with_twenty_men_each_and_white_to_move() {
+        let mut game = Game::new();
+        assert_eq!(game.figure_map().len(), 40);
+        assert_eq!(game.side_to_move(), Color::White);
+        assert_eq!(game.legal_moves().len(), 9);
+    }
+
+    #[test]
+    fn make_move_and_undo_restore_position() {
+        let mut game = Game::new();
+        let moves = game.legal_moves();
+        game.make_move(moves[0].clone()).unwrap();
+        assert_eq!(game.side_to_move(), Color::Black);
+        assert_eq!(game.history().len(), 1);
+
+        assert!(game.undo());
+        assert_eq!(game.side_to_move(), Color::White);
+        assert_eq!(game.figure_map().len(), 40);
+        assert!(game.figure_map().contains_key(&moves[0][0].moved_figure_no));
+        assert!(!game.undo());
+    }
+
+    #[test]
+    fn make_move_rejects_move_of_side_not_on_move() {
+        let mut game = Game::new();
+        let moves = game.legal_moves();
+        let other_moves = moves
+            .iter()
+            .find(|mov| {
+                mov[0].moved_figure_no != moves[0][0].moved_figure_no
+                    && mov[0].square_no != moves[0][0].square_no
+            })
+            .unwrap()
+            .clone();
+        game.make_move(moves[0].clone()).unwrap();
+        assert_eq!(game.make_move(other_moves), Err(MoveError::NotYourTurn));
+        assert_eq!(game.side_to_move(), Color::Black);
+        assert_eq!(game.history().len(), 1);
+    }
+
+    #[test]
+    fn outcome_follows_the_side_to_move() {
+        assert_eq!(Game::new().outcome(), GameOutcome::Ongoing);
+        //Black man on 90 is stuck, white man on 50 is not
+        let figure_map = HashMap::from([
+            (
+                90,
+                IFigure {
+                    color: "black".to_string(),
+                    kind: "man".to_string(),
+                },
+            ),
+            (
+                50,
+                IFigure {
+                    color: "white".to_string(),
+                    kind: "man".to_string(),
+                },
+            ),
+        ]);
+        let mut game = Game::from_figure_map(figure_map.clone(), Color::Black);
+        assert_eq!(game.outcome(), GameOutcome::WhiteWins);
+        let mut game = Game::from_figure_map(figure_map, Color::White);
+        assert_eq!(game.outcome(), GameOutcome::Ongoing);
+    }
+}
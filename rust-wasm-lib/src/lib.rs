@@ -1,9 +1,46 @@
 use core::fmt;
+use instant::Duration;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::cmp;
 use std::{collections::HashMap, vec};
 use wasm_bindgen::prelude::*;
-use web_sys::console;
+
+mod bitboard;
+mod difficulty;
+mod dxp;
+mod eval;
+mod fen;
+mod game;
+mod notation;
+mod outcome;
+mod pdn;
+mod perft;
+mod search;
+mod transposition;
+mod validation;
+use bitboard::{bit_square, bits, square_bit, BitMove, Position};
+pub use difficulty::{Difficulty, DifficultySettings};
+pub use dxp::{
+    AcceptCode, BackCode, DxpConnection, DxpError, DxpGame, DxpMessage, DxpPlayer, EndReason,
+    GameRequest,
+};
+pub use eval::EvalWeights;
+pub use fen::{from_fen, to_fen, FenError};
+pub use game::Game;
+pub use notation::{
+    algebraic, from_algebraic, grid_square, move_notation, move_squares, parse_move, square_number,
+    NotationError,
+};
+pub use outcome::{DrawReason, GameOutcome};
+pub use pdn::{parse_pdn, write_pdn, PdnError, PdnGame, PdnLine, PdnMove, PdnResult};
+pub use perft::PerftDivision;
+pub use search::{AnalysisLine, SearchResult};
+use search::{Search, MAX_SEARCH_DEPTH, WIN_SCORE};
+pub use transposition::TranspositionTable;
+use transposition::{Bound, Entry};
+use validation::figure_map_from_js;
+pub use validation::MoveError;
 
 #[wasm_bindgen]
 pub fn init_panic_hook() {
@@ -23,6 +60,7 @@ export interface Move {
     square_no: number,
     captured_figure_no?: number,
     captured_figure?: IFigure,
+    promoted: boolean,
 }
 "#;
 
@@ -34,6 +72,9 @@ pub struct Move {
     square_no: i32,
     captured_figure_no: Option<i32>,
     captured_figure: Option<IFigure>,
+    //Man becomes a king when the move ends on the last row
+    #[serde(default)]
+    promoted: bool,
 }
 
 impl fmt::Debug for IFigure {
@@ -47,13 +88,10 @@ impl fmt::Display for IFigure {
     }
 }
 
+//Single hop in standard notation, like `32-28` or `19x30`
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "(moved_figure_no:{}, moved_figure:{}, sqare_no:{}, captured_figure_no:{:?})",
-            self.moved_figure_no, self.moved_figure, self.square_no, self.captured_figure_no
-        )
+        write!(f, "{}", move_notation(std::slice::from_ref(self)))
     }
 }
 
@@ -62,573 +100,496 @@ pub struct IFigure {
     color: String,
     kind: String,
 }
-enum Zone {
-    LeftBorder,
-    Middle,
-    RightBorder,
-}
 
-#[derive(Clone, Debug)]
-enum Direction {
-    RightUp = -9,
-    RightDown = 11,
-    LeftUp = -11,
-    LeftDown = 9,
-}
-
-trait GetMoves {
-    fn get_figure(&self) -> &IFigure;
-    fn get_figure_no(&self) -> &i32;
-
-    fn get_target_sqares(&self) -> Vec<Vec<i32>>;
-    fn get_poss_moves(&self, figure_map: &HashMap<i32, IFigure>) -> Vec<Move>;
-
-    fn add_poss_move_forward(
-        &self,
-        target_sqare_no: i32,
-        poss_moves: &mut Vec<Move>,
-        figure_map: &HashMap<i32, IFigure>,
-    ) {
-        match figure_map.get(&target_sqare_no) {
-            Some(captured_figure) => {
-                Self::try_capture(
-                    self,
-                    target_sqare_no,
-                    captured_figure,
-                    poss_moves,
-                    figure_map,
-                );
-            }
-            None => poss_moves.push(Move {
-                moved_figure_no: *self.get_figure_no(),
-                moved_figure: (*self.get_figure()).clone(),
-                square_no: target_sqare_no,
-                captured_figure_no: None,
-                captured_figure: None,
-            }),
+impl IFigure {
+    pub fn color(&self) -> Color {
+        match self.color.as_str() {
+            "white" => Color::White,
+            _ => Color::Black,
         }
     }
 
-    fn get_capture_direction(&self, captured_figure_no: i32) -> Direction {
-        let diff = captured_figure_no - self.get_figure_no();
-        if diff > 0 {
-            if diff % 11 == 0 {
-                Direction::RightDown
-            } else {
-                Direction::LeftDown
-            }
-        } else if diff % 11 == 0 {
-            Direction::LeftUp
-        } else {
-            Direction::RightUp
-        }
+    pub fn is_king(&self) -> bool {
+        self.kind == "king"
     }
 
-    fn try_capture(
-        &self,
-        captured_figure_no: i32,
-        captured_figure: &IFigure,
-        poss_moves: &mut Vec<Move>,
-        figure_map: &HashMap<i32, IFigure>,
-    ) {
-        //Check if captured figure is the enemy and it isn't by the border
-        if self.get_figure().color != captured_figure.color
-            //check if isn't at border
-            && ![0, 9].contains(&(captured_figure_no % 10)) 
-            //check board top
-            && !(0..10).contains(&captured_figure_no) 
-            //check board floor
-            && !(90..100).contains(&captured_figure_no)
-
-        {
-            //Check for possible block
-            let poss_block_figure_no =
-                captured_figure_no + self.get_capture_direction(captured_figure_no) as i32;
-            if figure_map.get(&poss_block_figure_no).is_none() {
-                poss_moves.push(Move {
-                    moved_figure_no: *self.get_figure_no(),
-                    moved_figure: (*self.get_figure()).clone(),
-                    square_no: poss_block_figure_no,
-                    captured_figure_no: Some(captured_figure_no),
-                    captured_figure: Some((*captured_figure).clone()),
-                });
-            }
+    fn crowned(&self) -> IFigure {
+        IFigure {
+            color: self.color.clone(),
+            kind: "king".to_string(),
         }
     }
 }
 
-struct King {
-    figure_no: i32,
-    figure: IFigure,
-}
-
-impl GetMoves for King {
-    fn get_figure(&self) -> &IFigure {
-        &self.figure
-    }
-    fn get_figure_no(&self) -> &i32 {
-        &self.figure_no
-    }
-
-    fn get_poss_moves(&self, figure_map: &HashMap<i32, IFigure>) -> Vec<Move> {
-        let mut poss_moves: Vec<Move> = vec![];
-        let moves_array = Self::get_target_sqares(self);
-        for moves in moves_array {
-            for target_sqare_no in moves {
-                let len_before = poss_moves.len();
-                Self::add_poss_move_forward(self, target_sqare_no, &mut poss_moves, figure_map);
-                let is_capture = match poss_moves.last() {
-                    Some(mov) => mov.captured_figure_no.is_some(),
-                    None => false,
-                };
-                if poss_moves.len() == len_before || is_capture {
-                    break;
-                }
-            }
-        }
-        poss_moves
-    }
+#[wasm_bindgen(typescript_custom_section)]
+const possible_moves: &'static str = r#"
+export function possible_moves(clicked_sqare_no: number, figure_map: Map<number, IFigure>): Move[];
+"#;
 
-    fn get_target_sqares(&self) -> Vec<Vec<i32>> {
-        let right_up_moves = cmp::min(9 - self.figure_no % 10, self.figure_no / 10);
-        let right_up = Self::get_target_sqares_by_direction(self, right_up_moves, 9, true);
-        let left_up_moves = cmp::min(self.figure_no % 10, self.figure_no / 10);
-        let left_up = Self::get_target_sqares_by_direction(self, left_up_moves, 11, true);
-        let right_down_moves = cmp::min(9 - self.figure_no % 10, 9 - self.figure_no / 10);
-        let right_down = Self::get_target_sqares_by_direction(self, right_down_moves, 11, false);
-        let left_down_moves = cmp::min(self.figure_no % 10, 9 - self.figure_no / 10);
-        let left_down = Self::get_target_sqares_by_direction(self, left_down_moves, 9, false);
-        vec![right_up, right_down, left_up, left_down]
-    }
-}
+#[wasm_bindgen(skip_typescript)]
+pub fn possible_moves(moved_figure_no: i32, figure_map: JsValue) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+    let board: Board = Board::new(&mut figure_map);
+    let poss_moves = board.figure_moves(moved_figure_no);
 
-impl King {
-    fn new(figure_no: i32, figure: IFigure) -> Self {
-        Self { figure_no, figure }
-    }
-    fn get_target_sqares_by_direction(&self, moves_no: i32, step: i32, moves_up: bool) -> Vec<i32> {
-        let mut moves: Vec<i32> = vec![];
-        if moves_up {
-            for mov in 1..=moves_no {
-                moves.push(self.figure_no - mov * step);
-            }
-        } else {
-            for mov in 1..=moves_no {
-                moves.push(self.figure_no + mov * step)
-            }
-        }
-        moves
-    }
+    Ok(serde_wasm_bindgen::to_value(&poss_moves)?)
 }
 
-struct Man {
-    figure_no: i32,
-    figure: IFigure,
-    zone: Zone,
+#[wasm_bindgen]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Color {
+    Black,
+    White,
 }
 
-impl GetMoves for Man {
-    fn get_figure(&self) -> &IFigure {
-        &self.figure
-    }
-    fn get_figure_no(&self) -> &i32 {
-        &self.figure_no
-    }
-
-    fn get_poss_moves(&self, figure_map: &HashMap<i32, IFigure>) -> Vec<Move> {
-        let mut poss_moves: Vec<Move> = vec![];
-        let all_moves = Self::get_target_sqares(self);
-        let target_sqares_forward = &all_moves[0];
-        let target_sqares_backward = &all_moves[1];
-
-        for target_sqare_no in target_sqares_forward {
-            Self::add_poss_move_forward(self, *target_sqare_no, &mut poss_moves, figure_map);
+impl Color {
+    fn opponent(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
         }
-        for target_sqare_no in target_sqares_backward {
-            Self::add_poss_move_backwards(self, *target_sqare_no, &mut poss_moves, figure_map);
-        }
-
-        poss_moves
     }
 
-    fn get_target_sqares(&self) -> Vec<Vec<i32>> {
-        let mut target_sqares_forward: Vec<i32> = match self.zone {
-            Zone::LeftBorder => vec![11],
-            Zone::Middle => vec![9, 11],
-            Zone::RightBorder => vec![9],
-        };
-        if self.figure.color == "white" {
-            for sqare_no in &mut target_sqares_forward {
-                *sqare_no += self.figure_no - 20;
-            }
-        } else {
-            for sqare_no in &mut target_sqares_forward {
-                *sqare_no += self.figure_no;
-            }
-        }
-        let mut target_sqares_backward = target_sqares_forward.clone();
-        if self.figure.color == "white" {
-            for sqare_no in &mut target_sqares_backward {
-                *sqare_no += 20;
-            }
-        } else {
-            for sqare_no in &mut target_sqares_backward {
-                *sqare_no -= 20;
-            }
+    //Color as written in IFigure
+    fn as_str(&self) -> &'static str {
+        match self {
+            Color::White => "white",
+            Color::Black => "black",
         }
-        vec![target_sqares_forward, target_sqares_backward]
     }
 }
 
-impl Man {
-    fn new(figure_no: i32, figure: IFigure) -> Self {
-        let zone: Zone = match figure_no % 10 {
-            0 => Zone::LeftBorder,
-            9 => Zone::RightBorder,
-            _ => Zone::Middle,
-        };
-        Self {
-            figure_no,
-            figure,
-            zone,
-        }
-    }
-    fn add_poss_move_backwards(
-        &self,
-        captured_figure_no: i32,
-        poss_moves: &mut Vec<Move>,
-        figure_map: &HashMap<i32, IFigure>,
-    ) {
-        if let Some(captured_figure) = figure_map.get(&captured_figure_no) {
-            Self::try_capture(
-                self,
-                captured_figure_no,
-                captured_figure,
-                poss_moves,
-                figure_map,
-            );
+fn initial_figure_map() -> HashMap<i32, IFigure> {
+    //Men are placed on dark squares of the first and last four rows
+    let mut figure_map: HashMap<i32, IFigure> = HashMap::new();
+    for sqare_no in 0..100 {
+        if (sqare_no / 10 + sqare_no % 10) % 2 == 0 {
+            continue;
         }
+        let color = match sqare_no {
+            0..=39 => "black",
+            60..=99 => "white",
+            _ => continue,
+        };
+        figure_map.insert(
+            sqare_no,
+            IFigure {
+                color: color.to_string(),
+                kind: "man".to_string(),
+            },
+        );
     }
-}
-
-fn get_poss_moves(
-    moved_figure_no: i32,
-    moved_figure: &IFigure,
-    figure_map: &HashMap<i32, IFigure>,
-) -> Vec<Move> {
-    //Get possible moves for either figure
-    let poss_moves: Vec<Move> = if moved_figure.kind == "man" {
-        let man = Man::new(moved_figure_no, (*moved_figure).clone());
-        man.get_poss_moves(figure_map)
-    } else {
-        let king = King::new(moved_figure_no, (*moved_figure).clone());
-        king.get_poss_moves(figure_map)
-    };
-    poss_moves
+    figure_map
 }
 
 #[wasm_bindgen(typescript_custom_section)]
 const possible_moves: &'static str = r#"
-export function possible_moves(clicked_sqare_no: number, figure_map: Map<number, IFigure>): Move[];
+export function forced_moves(color: Color, figure_map: Map<number, IFigure>): Move[];
 "#;
 
 #[wasm_bindgen(skip_typescript)]
-pub fn possible_moves(moved_figure_no: i32, figure_map: JsValue) -> Result<JsValue, JsError> {
-    let figure_map: HashMap<i32, IFigure> = serde_wasm_bindgen::from_value(figure_map)?;
-
-    let moved_figure: IFigure = if let Some(figure) = figure_map.get(&moved_figure_no) {
-        figure.clone()
-    } else {
-        IFigure::default()
-    };
+pub fn forced_moves(color: Color, figure_map: JsValue) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+    let board: Board = Board::new(&mut figure_map);
+    let forced_moves = board.capture_sequences(&color);
+    let mut first_forced_moves: Vec<Move> = vec![];
+    for mov in &forced_moves {
+        first_forced_moves.push(mov[0].clone());
+    }
+    Ok(serde_wasm_bindgen::to_value(&first_forced_moves)?)
+}
 
-    let poss_moves = get_poss_moves(moved_figure_no, &moved_figure, &figure_map);
+#[wasm_bindgen(typescript_custom_section)]
+const CaptureSequence: &'static str = r#"
+export interface CaptureSequence {
+    moves: Move[],
+    path: number[],
+    captured_figure_nos: number[],
+}
+"#;
 
-    Ok(serde_wasm_bindgen::to_value(&poss_moves)?)
+/// Whole capture sequence: every hop, the squares the figure visits and what it takes.
+#[derive(Clone, Serialize, Debug)]
+pub struct CaptureSequence {
+    moves: Vec<Move>,
+    //Starting square followed by every landing square
+    path: Vec<i32>,
+    captured_figure_nos: Vec<i32>,
 }
 
-#[wasm_bindgen]
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
-pub enum Color {
-    Black,
-    White,
+impl From<Vec<Move>> for CaptureSequence {
+    fn from(moves: Vec<Move>) -> Self {
+        let mut path: Vec<i32> = moves
+            .iter()
+            .take(1)
+            .map(|mov| mov.moved_figure_no)
+            .collect();
+        path.extend(moves.iter().map(|mov| mov.square_no));
+        let captured_figure_nos = moves
+            .iter()
+            .filter_map(|mov| mov.captured_figure_no)
+            .collect();
+        CaptureSequence {
+            moves,
+            path,
+            captured_figure_nos,
+        }
+    }
 }
 
 #[wasm_bindgen(typescript_custom_section)]
 const possible_moves: &'static str = r#"
-export function get_winner(figure_map: Map<number, IFigure>): Color?;
+export function forced_sequences(color: Color, figure_map: Map<number, IFigure>): CaptureSequence[];
 "#;
 
 #[wasm_bindgen(skip_typescript)]
-pub fn get_winner(figure_map: JsValue) -> Result<JsValue, JsError> {
-    let mut figure_map: HashMap<i32, IFigure> = serde_wasm_bindgen::from_value(figure_map)?;
+pub fn forced_sequences(color: Color, figure_map: JsValue) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
     let board: Board = Board::new(&mut figure_map);
-    let result = board.get_winner();
-    Ok(serde_wasm_bindgen::to_value(&result)?)
+    let sequences: Vec<CaptureSequence> = board
+        .capture_sequences(&color)
+        .into_iter()
+        .map(CaptureSequence::from)
+        .collect();
+    Ok(serde_wasm_bindgen::to_value(&sequences)?)
 }
 
 #[wasm_bindgen(typescript_custom_section)]
 const possible_moves: &'static str = r#"
-export function forced_moves(color: Color, figure_map: Map<number, IFigure>): Move[];
+export function get_best_move(color: Color, figure_map: Map<number, IFigure>, time_ms: number): SearchResult;
 "#;
 
-#[wasm_bindgen(skip_typescript)]
-pub fn forced_moves(color: Color, figure_map: JsValue) -> Result<JsValue, JsError> {
-    let mut figure_map: HashMap<i32, IFigure> = serde_wasm_bindgen::from_value(figure_map)?;
-    let mut board: Board = Board::new(&mut figure_map);
-    let forced_moves = board.get_forced_moves(&color);
-    let mut first_forced_moves: Vec<Move> = vec![];
-    for mov in &forced_moves {
-        first_forced_moves.push(mov[0].clone());
-    }
-    Ok(serde_wasm_bindgen::to_value(&first_forced_moves)?)
+thread_local! {
+    static TRANSPOSITION_TABLE: RefCell<TranspositionTable> = RefCell::default();
 }
 
-#[wasm_bindgen(typescript_custom_section)]
-const possible_moves: &'static str = r#"
-export function get_best_move(color: Color, figure_map: Map<number, IFigure>): Move[];
-"#;
+/// Sets memory used by the transposition table of `get_best_move`, clears the table.
+#[wasm_bindgen]
+pub fn set_hash_size(megabytes: usize) {
+    TRANSPOSITION_TABLE.with(|tt| *tt.borrow_mut() = TranspositionTable::with_memory(megabytes));
+}
 
+/// Best move of the deepest iteration completed within `time_ms`, with the depth reached.
 #[wasm_bindgen(skip_typescript)]
-pub fn get_best_move(color: Color, figure_map: JsValue) -> Result<JsValue, JsError> {
-    let mut figure_map: HashMap<i32, IFigure> = serde_wasm_bindgen::from_value(figure_map)?;
-    let start = instant::Instant::now();
+pub fn get_best_move(color: Color, figure_map: JsValue, time_ms: u32) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
     let mut board: Board = Board::new(&mut figure_map);
-    let (_, mov) = board.minimax(10, i32::MIN, i32::MAX, color);
-    let elapsed = start.elapsed();
-    console::log_1(&format!("Elapsed: {elapsed:?}").into());
-    Ok(serde_wasm_bindgen::to_value(&mov)?)
+    let result = TRANSPOSITION_TABLE.with(|tt| {
+        let mut tt = tt.borrow_mut();
+        let mut search = Search::new(&mut tt);
+        board.iterative_deepening(
+            &mut search,
+            Duration::from_millis(time_ms.into()),
+            MAX_SEARCH_DEPTH,
+            color,
+        )
+    });
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+//State taken back by unmake, the position is small enough to be copied
+#[derive(Clone, Copy)]
+struct Undo {
+    position: Position,
+    hash: u64,
 }
 
 struct Board<'a> {
     figure_map: &'a mut HashMap<i32, IFigure>,
+    //Same figures as bit masks, used for move generation
+    position: Position,
+    //Zobrist key of the figures, updated incrementally as moves are made
+    hash: u64,
 }
 
 impl Board<'_> {
-    fn new(figure_map: &mut HashMap<i32, IFigure>) -> Board {
-        Board { figure_map }
+    fn new(figure_map: &mut HashMap<i32, IFigure>) -> Board<'_> {
+        let hash = figure_map.iter().fold(0, |hash, (figure_no, figure)| {
+            hash ^ transposition::figure_key(*figure_no, figure)
+        });
+        let position = Position::from_figure_map(figure_map);
+        Board {
+            figure_map,
+            position,
+            hash,
+        }
+    }
+
+    /// Transposition key of the position with `color` to move.
+    fn position_key(&self, color: Color) -> u64 {
+        transposition::position_key(self.hash, &color)
+    }
+
+    fn update_hash(&mut self, mov: &Move) {
+        //Xor is its own inverse, so the same update undoes the move
+        let figure = if mov.promoted {
+            mov.moved_figure.crowned()
+        } else {
+            mov.moved_figure.clone()
+        };
+        self.hash ^= transposition::figure_key(mov.moved_figure_no, &mov.moved_figure)
+            ^ transposition::figure_key(mov.square_no, &figure);
+        if let (Some(captured_figure_no), Some(captured_figure)) =
+            (mov.captured_figure_no, &mov.captured_figure)
+        {
+            self.hash ^= transposition::figure_key(captured_figure_no, captured_figure);
+        }
     }
 
     fn make_move(&mut self, mov: &Move) {
         //Making the move
+        self.update_hash(mov);
         self.figure_map.remove(&mov.moved_figure_no);
-        self.figure_map
-            .insert(mov.square_no, mov.moved_figure.clone());
+        let figure = if mov.promoted {
+            mov.moved_figure.crowned()
+        } else {
+            mov.moved_figure.clone()
+        };
+        self.position.remove(mov.moved_figure_no);
+        self.position.put(mov.square_no, &figure);
+        self.figure_map.insert(mov.square_no, figure);
         if let Some(captured_figure_no) = mov.captured_figure_no {
             self.figure_map.remove(&captured_figure_no);
+            self.position.remove(captured_figure_no);
         }
     }
 
-    fn make_moves(&mut self, moves: &Vec<Move>) {
+    fn make_moves(&mut self, moves: &[Move]) {
         for mov in moves {
             self.make_move(mov);
         }
     }
 
     fn unmake_move(&mut self, mov: &Move) {
-        //Unmaking the move;
+        //Unmaking the move, moved figure is stored before a possible promotion
+        self.update_hash(mov);
+        self.figure_map.remove(&mov.square_no);
+        self.position.remove(mov.square_no);
         self.figure_map
             .insert(mov.moved_figure_no, mov.moved_figure.clone());
-        self.figure_map.remove(&mov.square_no);
+        self.position.put(mov.moved_figure_no, &mov.moved_figure);
         if let Some(captured_figure_no) = mov.captured_figure_no {
             if let Some(captured_figure) = &mov.captured_figure {
                 self.figure_map
                     .insert(captured_figure_no, (*captured_figure).clone());
+                self.position.put(captured_figure_no, captured_figure);
             }
         }
     }
 
-    fn unmake_moves(&mut self, moves: &Vec<Move>) {
-        for mov in moves {
+    fn unmake_moves(&mut self, moves: &[Move]) {
+        for mov in moves.iter().rev() {
             self.unmake_move(mov);
         }
     }
 
-    fn get_forced_moves(&mut self, color: &Color) -> Vec<Vec<Move>> {
-        //Get moves for color
-        let figures = self.figure_map.iter().filter(|&(_, figure)| {
-            figure.color
-                == match color {
-                    Color::White => "white",
-                    Color::Black => "black",
-                }
-        });
-        let mut capture_moves: Vec<Move> = vec![];
-        for (figure_no, figure) in figures {
-            let moves: Vec<Move> = get_poss_moves(*figure_no, figure, self.figure_map)
-                .into_iter()
-                .filter(|mov| mov.captured_figure_no.is_some())
-                .collect();
-            for mov in moves {
-                capture_moves.push(mov);
+    /// Plays a generated move on the bitboards only, the figure map is left as it was.
+    fn make(&mut self, mov: BitMove) -> Undo {
+        let undo = Undo {
+            position: self.position,
+            hash: self.hash,
+        };
+        let position = &self.position;
+        if let Some(color) = position.color_at(mov.from.into()) {
+            let is_king = position.is_king(mov.from.into());
+            self.hash ^= transposition::bit_key(mov.from.into(), color, is_king)
+                ^ transposition::bit_key(mov.to.into(), color, is_king || mov.promoted);
+            for captured_bit in bits(mov.captured) {
+                let is_king = position.is_king(captured_bit);
+                self.hash ^= transposition::bit_key(captured_bit, color.opponent(), is_king);
             }
         }
-        let (_depth, mut moves_vector) = self.get_forced_from_captures(&capture_moves);
-        for moves in &mut moves_vector {
-            moves.reverse();
-        }
-        moves_vector
+        self.position.make(mov);
+        undo
     }
 
-    fn get_forced_from_captures(&mut self, capture_moves: &Vec<Move>) -> (i32, Vec<Vec<Move>>) {
-        //Recurrentlly getting all forced moves with most multi-captures
-        if capture_moves.is_empty() {
-            return (0, vec![]);
-        }
+    fn unmake(&mut self, undo: Undo) {
+        self.position = undo.position;
+        self.hash = undo.hash;
+    }
 
-        let mut tree_depths: Vec<(i32, Vec<Move>)> = vec![];
-        for mov in capture_moves {
-            self.make_move(mov);
-            //Geting new moves, moved figure doesn't change
-            let new_capture_moves: Vec<Move> =
-                get_poss_moves(mov.square_no, &mov.moved_figure, self.figure_map)
-                    .into_iter()
-                    .filter(|mov| mov.captured_figure_no.is_some())
-                    .collect();
-            //Recursivly get depth and moves
-            let (depth, moves) = self.get_forced_from_captures(&new_capture_moves);
-            self.unmake_move(mov);
-            //Add current move
-            if moves.len() == 0 {
-                tree_depths.push((depth + 1, vec![(*mov).clone()]));
-            };
-            let mut new_moves = moves;
-            for vec in &mut new_moves {
-                vec.push((*mov).clone());
-                tree_depths.push((depth + 1, vec.to_vec()));
-            }
-        }
+    fn to_moves(&self, bit_move: BitMove) -> Vec<Move> {
+        //Hop by hop format of the JS side, figures are looked up before the move is made
+        let moved_figure_no = bit_square(bit_move.from.into());
+        let moved_figure = self
+            .figure_map
+            .get(&moved_figure_no)
+            .cloned()
+            .unwrap_or_default();
+        let hops = self.position.hops(bit_move);
+        let mut from = moved_figure_no;
+        hops.iter()
+            .enumerate()
+            .map(|(hop_no, hop)| {
+                let captured_figure_no = hop.captured.map(bit_square);
+                let mov = Move {
+                    moved_figure_no: from,
+                    moved_figure: moved_figure.clone(),
+                    square_no: bit_square(hop.to),
+                    captured_figure_no,
+                    captured_figure: captured_figure_no
+                        .and_then(|captured_figure_no| self.figure_map.get(&captured_figure_no))
+                        .cloned(),
+                    //Man passing the last row during a capture doesn't become a king
+                    promoted: bit_move.promoted && hop_no + 1 == hops.len(),
+                };
+                from = mov.square_no;
+                mov
+            })
+            .collect()
+    }
 
-        let max_depth = tree_depths
-            .iter()
-            .max_by_key(|(depth, _)| depth)
-            .unwrap_or(&(i32::MIN, vec![]))
-            .0;
-        let mut max_depth_vectors: Vec<Vec<Move>> = vec![];
-        let (_, moves_vectors): (Vec<i32>, Vec<Vec<Move>>) = tree_depths
+    fn figure_moves(&self, figure_no: i32) -> Vec<Move> {
+        //Single steps of one figure, captures aren't forced
+        let Some(bit) = square_bit(figure_no) else {
+            return vec![];
+        };
+        self.position
+            .figure_moves(bit)
             .into_iter()
-            .filter(|(depth, _)| *depth == max_depth)
-            .unzip();
-        for moves in moves_vectors {
-            max_depth_vectors.push(moves);
-        }
-        (max_depth, max_depth_vectors)
+            .flat_map(|bit_move| self.to_moves(bit_move))
+            .collect()
     }
 
-    fn get_available_moves(&mut self, color: &Color) -> Vec<Vec<Move>> {
-        let forced_moves = self.get_forced_moves(color);
-        if forced_moves.iter().count() > 0 {
-            return forced_moves;
-        }
-        let mut poss_moves: Vec<Vec<Move>> = vec![];
-        let figures = self.figure_map.iter().filter(|&(_, figure)| {
-            figure.color
-                == match color {
-                    Color::White => "white",
-                    Color::Black => "black",
-                }
-        });
-        for (moved_figure_no, moved_figure) in figures {
-            for mov in get_poss_moves(*moved_figure_no, moved_figure, self.figure_map) {
-                poss_moves.push(vec![mov]);
-            }
-        }
-        poss_moves
+    fn get_forced_moves(&self, color: &Color, moves: &mut Vec<BitMove>) {
+        self.position.captures(*color, moves);
     }
 
-    fn get_winner(&self) -> Option<Color> {
-        let mut result: Option<Color> = None;
-        for color in ["black", "white"] {
-            let any_poss_moves = self
-                .figure_map
-                .iter()
-                .filter(|&(_, figure)| figure.color == color)
-                .any(|(figure_no, figure)| {
-                    let poss_moves = get_poss_moves(*figure_no, figure, self.figure_map);
-                    !poss_moves.is_empty()
-                });
-            let any_figure = self
-                .figure_map
-                .iter()
-                .any(|(_, figure)| figure.color == color);
-            if !any_poss_moves || !any_figure {
-                if color == "black" {
-                    result = Some(Color::White);
-                } else {
-                    result = Some(Color::Black);
-                };
-            }
-        }
-        result
+    fn get_available_moves(&self, color: &Color, moves: &mut Vec<BitMove>) {
+        self.position.legal_moves(*color, moves);
     }
 
-    fn get_rating(&self) -> i32 {
-        let mut color_rating: HashMap<&str, i32> =
-            [("white", 0), ("black", 0)].iter().cloned().collect();
-        for color in ["white", "black"] {
-            for (_, figure) in self
-                .figure_map
-                .iter()
-                .filter(|(_, figure)| figure.color == *color)
-            {
-                if figure.kind == "man" {
-                    *color_rating.get_mut(color).unwrap() += 1;
-                } else {
-                    *color_rating.get_mut(color).unwrap() += 3;
-                }
-            }
-        }
-        color_rating["white"] - color_rating["black"]
+    /// Mandatory capture sequences of `color` converted for the JS side.
+    fn capture_sequences(&self, color: &Color) -> Vec<Vec<Move>> {
+        let mut moves: Vec<BitMove> = vec![];
+        self.get_forced_moves(color, &mut moves);
+        moves.into_iter().map(|mov| self.to_moves(mov)).collect()
+    }
+
+    /// Legal moves of `color` converted for the JS side.
+    fn legal_moves(&self, color: &Color) -> Vec<Vec<Move>> {
+        let mut moves: Vec<BitMove> = vec![];
+        self.get_available_moves(color, &mut moves);
+        moves.into_iter().map(|mov| self.to_moves(mov)).collect()
     }
 
     fn minimax(
         &mut self,
+        search: &mut Search,
         target_deph: i32,
         alpha: i32,
         beta: i32,
         color: Color,
-    ) -> (i32, Vec<Move>) {
-        if target_deph == 0 || self.get_winner().is_some() {
-            return (self.get_rating(), vec![]);
+    ) -> (i32, Option<BitMove>) {
+        if search.should_stop() {
+            return (0, None);
+        }
+        //Side to move without a move has lost, sooner losses score worse
+        if !self.position.has_moves(color) {
+            let score = WIN_SCORE - search.ply as i32;
+            return match color {
+                Color::White => (-score, None),
+                Color::Black => (score, None),
+            };
+        }
+        //Repeating a position or running into a move limit can't win anything
+        if search.ply > 0 && search.positions.draw_reason(2).is_some() {
+            search.history_draws += 1;
+            return (0, None);
+        }
+        if target_deph == 0 {
+            return (self.quiescence(search, alpha, beta, color, 0), None);
         }
 
-        let moves_vector = self.get_available_moves(&color);
-        let mut best_move: Vec<Move> = vec![];
-        if color == Color::White {
-            let mut bestval = i32::MIN;
-            let mut alpha = alpha;
-            for mov in &moves_vector {
-                self.make_moves(mov);
-                let (value, _) = self.minimax(target_deph - 1, alpha, beta, Color::Black);
-                self.unmake_moves(mov);
-                if value > bestval {
-                    bestval = value;
-                    best_move = mov.to_vec();
-                }
-                alpha = cmp::max(alpha, bestval);
-                if beta <= alpha {
-                    break;
-                }
+        //Reuse results of the position reached earlier by a different move order
+        let key = transposition::position_key(self.hash, &color);
+        let (alpha_orig, beta_orig) = (alpha, beta);
+        let (mut alpha, mut beta) = (alpha, beta);
+        let hash_move = search.tt.probe(key).and_then(|entry| entry.best_move);
+        if let Some(&entry) = search
+            .tt
+            .probe(key)
+            .filter(|entry| entry.depth >= target_deph)
+        {
+            let value = search.value_from_tt(entry.value);
+            match entry.bound {
+                Bound::Exact => return (value, entry.best_move),
+                Bound::Lower => alpha = cmp::max(alpha, value),
+                Bound::Upper => beta = cmp::min(beta, value),
             }
-            (bestval, best_move)
-        } else {
-            let mut bestval = i32::MAX;
-            let mut beta = beta;
-            for mov in &moves_vector {
-                self.make_moves(mov);
-                let (value, _) = self.minimax(target_deph - 1, alpha, beta, Color::White);
-                self.unmake_moves(mov);
-                if value < bestval {
-                    bestval = value;
-                    best_move = mov.to_vec();
+            if beta <= alpha {
+                return (value, entry.best_move);
+            }
+        }
+        let history_draws = search.history_draws;
+
+        //Move list of this ply is reused by every node searched at the same distance from the root
+        let mut moves_vector = search.take_move_list();
+        self.get_available_moves(&color, &mut moves_vector);
+        search.order_moves(&mut moves_vector, hash_move, &self.position);
+        let mut best_move: Option<BitMove> = None;
+        let mut bestval = match color {
+            Color::White => i32::MIN,
+            Color::Black => i32::MAX,
+        };
+        for &mov in &moves_vector {
+            let undo = self.make_searched(search, mov, color);
+            search.ply += 1;
+            let (value, _) = self.minimax(search, target_deph - 1, alpha, beta, color.opponent());
+            search.ply -= 1;
+            self.unmake_searched(search, undo);
+            if search.stopped() {
+                break;
+            }
+            match color {
+                Color::White => {
+                    if value > bestval {
+                        bestval = value;
+                        best_move = Some(mov);
+                    }
+                    alpha = cmp::max(alpha, bestval);
                 }
-                beta = cmp::min(beta, bestval);
-                if beta <= alpha {
-                    break;
+                Color::Black => {
+                    if value < bestval {
+                        bestval = value;
+                        best_move = Some(mov);
+                    }
+                    beta = cmp::min(beta, bestval);
                 }
             }
-            (bestval, best_move)
+            if beta <= alpha {
+                search.record_cutoff(mov, target_deph);
+                break;
+            }
+        }
+        search.put_move_list(moves_vector);
+
+        //Interrupted search isn't complete, it can't be stored
+        if search.stopped() {
+            return (bestval, best_move);
         }
+        let bound = if bestval <= alpha_orig {
+            Bound::Upper
+        } else if bestval >= beta_orig {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        //Value found through a draw of this line doesn't hold for other ways to the
+        //position, only the move is kept for ordering
+        let depth = if search.history_draws == history_draws {
+            target_deph
+        } else {
+            0
+        };
+        search.tt.store(Entry {
+            key,
+            depth,
+            value: search.value_to_tt(bestval),
+            bound,
+            best_move,
+        });
+        (bestval, best_move)
     }
 }
 
@@ -636,6 +597,214 @@ impl Board<'_> {
 mod tests {
     use super::*;
 
+    fn figure(color: &str, kind: &str) -> IFigure {
+        IFigure {
+            color: color.to_string(),
+            kind: kind.to_string(),
+        }
+    }
+
     #[test]
     fn it_works() {}
+
+    #[test]
+    fn man_reaching_last_row_becomes_king() {
+        let mut figure_map: HashMap<i32, IFigure> =
+            HashMap::from([(12, figure("white", "man")), (87, figure("black", "man"))]);
+        let mut board = Board::new(&mut figure_map);
+        let moves = board.legal_moves(&Color::White);
+        assert!(moves.iter().all(|mov| mov[0].promoted));
+
+        board.make_moves(&moves[0]);
+        assert_eq!(board.figure_map[&moves[0][0].square_no].kind, "king");
+        board.unmake_moves(&moves[0]);
+        assert_eq!(board.figure_map[&12].kind, "man");
+    }
+
+    #[test]
+    fn man_passing_last_row_during_capture_stays_man() {
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (23, figure("white", "man")),
+            (14, figure("black", "man")),
+            (16, figure("black", "man")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let moves = board.capture_sequences(&Color::White);
+        assert_eq!(moves.len(), 1);
+        let squares: Vec<i32> = moves[0].iter().map(|mov| mov.square_no).collect();
+        assert_eq!(squares, vec![5, 27]);
+        assert!(moves[0].iter().all(|mov| !mov.promoted));
+
+        board.make_moves(&moves[0]);
+        assert_eq!(board.figure_map[&27].kind, "man");
+        board.unmake_moves(&moves[0]);
+        assert_eq!(board.figure_map.len(), 3);
+        assert_eq!(board.figure_map[&23].kind, "man");
+    }
+
+    #[test]
+    fn capture_ending_on_last_row_promotes() {
+        let mut figure_map: HashMap<i32, IFigure> =
+            HashMap::from([(23, figure("white", "man")), (14, figure("black", "man"))]);
+        let mut board = Board::new(&mut figure_map);
+        let moves = board.capture_sequences(&Color::White);
+        assert_eq!(moves.len(), 1);
+        assert!(moves[0][0].promoted);
+
+        board.make_moves(&moves[0]);
+        assert_eq!(board.figure_map[&5].kind, "king");
+        assert!(!board.figure_map.contains_key(&14));
+    }
+
+    #[test]
+    fn king_can_land_on_every_empty_square_beyond_captured_figure() {
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (90, figure("white", "king")),
+            (72, figure("black", "man")),
+            (18, figure("white", "man")),
+        ]);
+        let board = Board::new(&mut figure_map);
+        let moves = board.figure_moves(90);
+        let mut landings: Vec<i32> = moves
+            .iter()
+            .filter(|mov| mov.captured_figure_no == Some(72))
+            .map(|mov| mov.square_no)
+            .collect();
+        landings.sort();
+        assert_eq!(landings, vec![27, 36, 45, 54, 63]);
+
+        assert_eq!(board.capture_sequences(&Color::White).len(), 5);
+    }
+
+    #[test]
+    fn king_landing_square_is_chosen_by_maximum_capture() {
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (90, figure("white", "king")),
+            (72, figure("black", "man")),
+            (34, figure("black", "man")),
+        ]);
+        let board = Board::new(&mut figure_map);
+        let moves = board.capture_sequences(&Color::White);
+        assert_eq!(moves.len(), 3);
+        assert!(moves
+            .iter()
+            .all(|mov| mov.len() == 2 && mov[0].square_no == 45));
+        let mut last_squares: Vec<i32> = moves.iter().map(|mov| mov[1].square_no).collect();
+        last_squares.sort();
+        assert_eq!(last_squares, vec![1, 12, 23]);
+
+        let sequence = CaptureSequence::from(moves[0].clone());
+        assert_eq!(sequence.path[..2], [90, 45]);
+        assert_eq!(sequence.captured_figure_nos, vec![72, 34]);
+    }
+
+    #[test]
+    fn king_cannot_pass_through_figure_captured_earlier_in_sequence() {
+        //Lifting 72 right away would let the king come back through it and capture 61 as well
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (90, figure("white", "king")),
+            (72, figure("black", "man")),
+            (56, figure("black", "man")),
+            (76, figure("black", "man")),
+            (61, figure("black", "man")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let moves = board.capture_sequences(&Color::White);
+        assert!(!moves.is_empty());
+        for mov in &moves {
+            let captured: Vec<i32> = mov
+                .iter()
+                .filter_map(|mov| mov.captured_figure_no)
+                .collect();
+            assert_eq!(captured, vec![72, 56, 76]);
+        }
+
+        //Captured figures are lifted once the sequence is played and come back on unmake
+        let position = board.position;
+        board.make_moves(&moves[0]);
+        assert_eq!(board.figure_map.len(), 2);
+        assert!([72, 56, 76]
+            .iter()
+            .all(|square_no| !board.figure_map.contains_key(square_no)));
+        assert_eq!(board.position, Position::from_figure_map(board.figure_map));
+        board.unmake_moves(&moves[0]);
+        assert_eq!(board.figure_map.len(), 5);
+        assert_eq!(board.position, position);
+    }
+
+    #[test]
+    fn king_cannot_land_on_figure_captured_earlier_in_sequence() {
+        //Capturing 61 from 50 would need a landing on 72, which is still occupied
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (90, figure("white", "king")),
+            (72, figure("black", "man")),
+            (43, figure("black", "man")),
+            (41, figure("black", "man")),
+            (61, figure("black", "man")),
+        ]);
+        let board = Board::new(&mut figure_map);
+        let moves = board.capture_sequences(&Color::White);
+        assert!(!moves.is_empty());
+        for mov in &moves {
+            let captured: Vec<i32> = mov
+                .iter()
+                .filter_map(|mov| mov.captured_figure_no)
+                .collect();
+            assert_eq!(captured, vec![72, 43, 41]);
+            assert_eq!(mov.last().unwrap().square_no, 50);
+        }
+    }
+
+    #[test]
+    fn hash_is_updated_incrementally_by_make_and_unmake() {
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (23, figure("white", "man")),
+            (14, figure("black", "man")),
+            (16, figure("black", "man")),
+            (90, figure("white", "king")),
+            (72, figure("black", "king")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let start_hash = board.hash;
+        for color in [Color::White, Color::Black] {
+            let mut bit_moves: Vec<BitMove> = vec![];
+            board.get_available_moves(&color, &mut bit_moves);
+            for bit_move in bit_moves {
+                let moves = board.to_moves(bit_move);
+                let undo = board.make(bit_move);
+                let (bit_hash, position) = (board.hash, board.position);
+                board.unmake(undo);
+                board.make_moves(&moves);
+                let hash = board.hash;
+                assert_eq!(hash, Board::new(board.figure_map).hash);
+                assert_eq!((bit_hash, position), (hash, board.position));
+                board.unmake_moves(&moves);
+                assert_eq!(board.hash, start_hash);
+            }
+        }
+    }
+
+    #[test]
+    fn transposition_table_keeps_minimax_value() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        for depth in 1..=4 {
+            let (value, _) = board.minimax(
+                &mut Search::new(&mut TranspositionTable::with_memory(0)),
+                depth,
+                i32::MIN,
+                i32::MAX,
+                Color::White,
+            );
+            let (tt_value, tt_move) = board.minimax(
+                &mut Search::new(&mut TranspositionTable::default()),
+                depth,
+                i32::MIN,
+                i32::MAX,
+                Color::White,
+            );
+            assert_eq!(value, tt_value);
+            assert!(tt_move.is_some());
+        }
+    }
 }
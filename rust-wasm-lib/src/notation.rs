@@ -0,0 +1,370 @@
+use crate::validation::figure_map_from_js;
+use crate::{Board, Color, IFigure, Move, MoveError};
+use core::fmt;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const NotationError: &'static str = r#"
+export interface NotationError extends Error {
+    code: "INVALID_SQUARE" | "NOT_A_MOVE" | "AMBIGUOUS_MOVE" | "ILLEGAL_MOVE",
+}
+"#;
+
+/// Reason why a move in standard notation can't be played.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NotationError {
+    InvalidSquare(String),
+    NotAMove(String),
+    AmbiguousMove(String),
+    IllegalMove(String, MoveError),
+}
+
+impl NotationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            NotationError::InvalidSquare(_) => "INVALID_SQUARE",
+            NotationError::NotAMove(_) => "NOT_A_MOVE",
+            NotationError::AmbiguousMove(_) => "AMBIGUOUS_MOVE",
+            NotationError::IllegalMove(..) => "ILLEGAL_MOVE",
+        }
+    }
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::InvalidSquare(text) => {
+                write!(f, "\"{text}\" is not a square number between 1 and 50")
+            }
+            NotationError::NotAMove(text) => write!(f, "\"{text}\" is not a move"),
+            NotationError::AmbiguousMove(text) => write!(
+                f,
+                "{text} is ambiguous, the squares landed on have to be written"
+            ),
+            NotationError::IllegalMove(text, error) => write!(f, "{text} is illegal: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl From<NotationError> for JsValue {
+    fn from(error: NotationError) -> Self {
+        let js_error: JsValue = JsError::new(&error.to_string()).into();
+        let _ = js_sys::Reflect::set(&js_error, &"code".into(), &error.code().into());
+        js_error
+    }
+}
+
+/// Standard 1–50 number of a playable square of the 10x10 grid, counted row by row
+/// from the top left, so black starts on 1–20 and white on 31–50.
+#[wasm_bindgen]
+pub fn square_number(sqare_no: i32) -> Option<u32> {
+    let (row, col) = (sqare_no / 10, sqare_no % 10);
+    if !(0..100).contains(&sqare_no) || (row + col) % 2 == 0 {
+        return None;
+    }
+    Some((row * 5 + col / 2 + 1) as u32)
+}
+
+/// Square of the 10x10 grid with the standard number `number`.
+#[wasm_bindgen]
+pub fn grid_square(number: u32) -> Option<i32> {
+    if !(1..=50).contains(&number) {
+        return None;
+    }
+    let playable_no = number as i32 - 1;
+    let (row, col) = (playable_no / 5, playable_no % 5 * 2);
+    Some(row * 10 + col + (row + 1) % 2)
+}
+
+/// Algebraic coordinates of a playable square, files a–j from the left and
+/// ranks 1–10 from the white side, so square 46 is `a1` and square 5 is `j10`.
+#[wasm_bindgen]
+pub fn algebraic(sqare_no: i32) -> Option<String> {
+    square_number(sqare_no)?;
+    let (row, col) = (sqare_no / 10, sqare_no % 10);
+    Some(format!("{}{}", (b'a' + col as u8) as char, 10 - row))
+}
+
+/// Square of the 10x10 grid at algebraic coordinates like `a1`.
+#[wasm_bindgen]
+pub fn from_algebraic(text: &str) -> Option<i32> {
+    let mut chars = text.chars();
+    let file = chars.next().filter(|file| ('a'..='j').contains(file))?;
+    let rank: i32 = chars
+        .as_str()
+        .parse()
+        .ok()
+        .filter(|rank| (1..=10).contains(rank))?;
+    let sqare_no = (10 - rank) * 10 + (file as u8 - b'a') as i32;
+    square_number(sqare_no).map(|_| sqare_no)
+}
+
+/// Standard notation of a move given hop by hop, like `32-28` or `19x30x39`.
+pub fn move_notation(moves: &[Move]) -> String {
+    let Some(first) = moves.first() else {
+        return String::new();
+    };
+    let separator = if first.captured_figure_no.is_some() {
+        "x"
+    } else {
+        "-"
+    };
+    let squares: Vec<String> = std::iter::once(first.moved_figure_no)
+        .chain(moves.iter().map(|mov| mov.square_no))
+        .map(|sqare_no| square_number(sqare_no).unwrap_or_default().to_string())
+        .collect();
+    squares.join(separator)
+}
+
+/// Standard numbers of the start square, end square and the captured squares in
+/// ascending order of a move given hop by hop. Captures taking the same figures by
+/// another route have the same squares.
+pub fn move_squares(moves: &[Move]) -> (u32, u32, Vec<u32>) {
+    let number = |sqare_no: i32| square_number(sqare_no).unwrap_or_default();
+    let mut captured: Vec<u32> = moves
+        .iter()
+        .filter_map(|mov| mov.captured_figure_no.map(number))
+        .collect();
+    captured.sort_unstable();
+    let from = moves.first().map_or(0, |mov| number(mov.moved_figure_no));
+    let to = moves.last().map_or(0, |mov| number(mov.square_no));
+    (from, to, captured)
+}
+
+/// Squares of a move written in standard notation. Captures may list only the first
+/// and last square or any of the squares landed on in between.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MoveNotation {
+    squares: Vec<i32>,
+    capture: bool,
+}
+
+impl MoveNotation {
+    pub(crate) fn parse(text: &str) -> Result<MoveNotation, NotationError> {
+        let capture = text.contains('x');
+        let separator = if capture { 'x' } else { '-' };
+        let squares = text
+            .split(separator)
+            .map(|number| {
+                number
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(grid_square)
+                    .ok_or_else(|| NotationError::InvalidSquare(number.to_string()))
+            })
+            .collect::<Result<Vec<i32>, NotationError>>()?;
+        if squares.len() < 2 || (!capture && squares.len() > 2) {
+            return Err(NotationError::NotAMove(text.to_string()));
+        }
+        Ok(MoveNotation { squares, capture })
+    }
+
+    /// Whether the hop by hop move is the one written down.
+    pub(crate) fn matches(&self, moves: &[Move]) -> bool {
+        let (Some(first), Some(last)) = (moves.first(), moves.last()) else {
+            return false;
+        };
+        if first.captured_figure_no.is_some() != self.capture
+            || first.moved_figure_no != self.squares[0]
+            || last.square_no != self.squares[self.squares.len() - 1]
+        {
+            return false;
+        }
+        //Squares in between have to be landed on in the same order
+        let mut landings = moves[..moves.len() - 1].iter().map(|mov| mov.square_no);
+        self.squares[1..self.squares.len() - 1]
+            .iter()
+            .all(|sqare_no| landings.any(|landing| landing == *sqare_no))
+    }
+}
+
+impl Board<'_> {
+    /// Legal move of `color` written in standard notation.
+    pub(crate) fn notation_move(
+        &self,
+        color: Color,
+        text: &str,
+    ) -> Result<Vec<Move>, NotationError> {
+        let notation = MoveNotation::parse(text)?;
+        let legal_moves = self.legal_moves(&color);
+        let mut matching = legal_moves.iter().filter(|moves| notation.matches(moves));
+        match (matching.next(), matching.next()) {
+            (Some(moves), None) => Ok(moves.clone()),
+            (Some(_), Some(_)) => Err(NotationError::AmbiguousMove(text.to_string())),
+            (None, _) => {
+                let must_capture = legal_moves
+                    .first()
+                    .is_some_and(|moves| moves[0].captured_figure_no.is_some());
+                let error = match self.figure_map.get(&notation.squares[0]) {
+                    None => MoveError::EmptySquare,
+                    Some(figure) if figure.color != color.as_str() => MoveError::NotYourTurn,
+                    Some(_) if must_capture && !notation.capture => MoveError::CaptureIsMandatory,
+                    Some(_) => MoveError::IllegalMove,
+                };
+                Err(NotationError::IllegalMove(text.to_string(), error))
+            }
+        }
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const move_notation: &'static str = r#"
+export function move_notation(moves: Move[]): string;
+export function parse_move(color: Color, figure_map: Map<number, IFigure>, text: string): Move[];
+"#;
+
+#[wasm_bindgen(js_name = move_notation, skip_typescript)]
+pub fn move_notation_js(moves: JsValue) -> Result<String, JsError> {
+    let moves: Vec<Move> = serde_wasm_bindgen::from_value(moves)?;
+    Ok(move_notation(&moves))
+}
+
+/// Legal move of `color` in the position written in standard notation, like `32-28` or `19x30x39`.
+pub fn parse_move(
+    color: Color,
+    figure_map: &mut HashMap<i32, IFigure>,
+    text: &str,
+) -> Result<Vec<Move>, NotationError> {
+    Board::new(figure_map).notation_move(color, text)
+}
+
+#[wasm_bindgen(js_name = parse_move, skip_typescript)]
+pub fn parse_move_js(color: Color, figure_map: JsValue, text: &str) -> Result<JsValue, JsValue> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsValue>(figure_map)?;
+    let moves = parse_move(color, &mut figure_map, text)?;
+    Ok(serde_wasm_bindgen::to_value(&moves)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_figure_map, Game};
+
+    #[test]
+    fn square_numbers_follow_the_rows() {
+        assert_eq!(square_number(1), Some(1));
+        assert_eq!(square_number(10), Some(6));
+        assert_eq!(square_number(98), Some(50));
+        assert_eq!(square_number(0), None);
+        assert_eq!(square_number(100), None);
+        for number in 1..=50 {
+            assert_eq!(square_number(grid_square(number).unwrap()), Some(number));
+        }
+        assert_eq!(grid_square(0), None);
+        assert_eq!(grid_square(51), None);
+    }
+
+    #[test]
+    fn capture_notation_may_skip_landings() {
+        let full = MoveNotation::parse("19x30x39").unwrap();
+        let short = MoveNotation::parse("19x39").unwrap();
+        assert_eq!(
+            short.squares,
+            vec![grid_square(19).unwrap(), grid_square(39).unwrap()]
+        );
+        assert_eq!(full.squares.len(), 3);
+        assert!(full.capture && short.capture);
+        assert!(MoveNotation::parse("32-28-23").is_err());
+        assert!(MoveNotation::parse("32-51").is_err());
+        assert!(MoveNotation::parse("32").is_err());
+    }
+
+    #[test]
+    fn move_squares_list_the_captured_squares_in_order() {
+        let mut game = Game::from_fen("W:W32,50:B1,19,28").unwrap();
+        let legal_moves = game.legal_moves();
+        assert_eq!(legal_moves.len(), 1);
+        assert_eq!(move_squares(&legal_moves[0]), (32, 14, vec![19, 28]));
+        let mut game = Game::new();
+        let quiet: Vec<_> = game
+            .legal_moves()
+            .iter()
+            .map(|moves| move_squares(moves))
+            .collect();
+        assert!(quiet.contains(&(32, 28, vec![])));
+    }
+
+    #[test]
+    fn algebraic_coordinates_start_at_the_white_side() {
+        assert_eq!(algebraic(grid_square(46).unwrap()).as_deref(), Some("a1"));
+        assert_eq!(algebraic(grid_square(5).unwrap()).as_deref(), Some("j10"));
+        assert_eq!(algebraic(grid_square(32).unwrap()).as_deref(), Some("d4"));
+        assert_eq!(algebraic(0), None);
+        for sqare_no in (0..100).filter(|sqare_no| square_number(*sqare_no).is_some()) {
+            assert_eq!(
+                from_algebraic(&algebraic(sqare_no).unwrap()),
+                Some(sqare_no)
+            );
+        }
+        assert_eq!(from_algebraic("a2"), None);
+        assert_eq!(from_algebraic("k1"), None);
+        assert_eq!(from_algebraic("a11"), None);
+        assert_eq!(from_algebraic(""), None);
+    }
+
+    #[test]
+    fn moves_are_written_and_read_in_standard_notation() {
+        let mut game = Game::new();
+        let moves = game.parse_move("32-28").unwrap();
+        assert_eq!(move_notation(&moves), "32-28");
+        assert_eq!(moves[0].to_string(), "32-28");
+        game.make_move(moves).unwrap();
+        let moves = game.parse_move("19-23").unwrap();
+        game.make_move(moves).unwrap();
+        assert_eq!(
+            game.parse_move("28-22").unwrap_err(),
+            NotationError::IllegalMove("28-22".to_string(), MoveError::CaptureIsMandatory)
+        );
+        let capture = game.parse_move("28x19").unwrap();
+        assert_eq!(capture[0].to_string(), "28x19");
+        assert_eq!(
+            game.parse_move("33-29").unwrap_err(),
+            NotationError::IllegalMove("33-29".to_string(), MoveError::CaptureIsMandatory)
+        );
+        assert_eq!(
+            game.parse_move("24-30").unwrap_err(),
+            NotationError::IllegalMove("24-30".to_string(), MoveError::EmptySquare)
+        );
+        assert_eq!(
+            game.parse_move("32-51").unwrap_err(),
+            NotationError::InvalidSquare("51".to_string())
+        );
+    }
+
+    #[test]
+    fn full_capture_sequences_are_written_square_by_square() {
+        //White man on 34 takes 29 and 19, landing on 23 and 14
+        let (mut figure_map, color) = crate::from_fen("W:W34:B19,29").unwrap();
+        let moves = parse_move(color, &mut figure_map, "34x14").unwrap();
+        assert_eq!(move_notation(&moves), "34x23x14");
+        assert_eq!(
+            moves
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            vec!["34x23", "23x14"]
+        );
+        assert!(parse_move(color, &mut figure_map, "34x23x14").is_ok());
+        assert!(parse_move(color, &mut figure_map, "34x24x14").is_err());
+        assert_eq!(
+            parse_move(Color::Black, &mut initial_figure_map(), "34-30").unwrap_err(),
+            NotationError::IllegalMove("34-30".to_string(), MoveError::NotYourTurn)
+        );
+    }
+
+    #[test]
+    fn ambiguous_capture_needs_the_landing_squares() {
+        //31x22x33x24x15 and 31x42x33x24x15 take different figures
+        let (mut figure_map, color) = crate::from_fen("W:W19,31:B20,27,28,29,37,38").unwrap();
+        assert_eq!(
+            parse_move(color, &mut figure_map, "31x15").unwrap_err(),
+            NotationError::AmbiguousMove("31x15".to_string())
+        );
+        assert!(parse_move(color, &mut figure_map, "31x33x15").is_err());
+        let moves = parse_move(color, &mut figure_map, "31x42x15").unwrap();
+        assert_eq!(move_notation(&moves), "31x42x33x24x15");
+    }
+}
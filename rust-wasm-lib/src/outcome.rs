@@ -0,0 +1,442 @@
+use crate::bitboard::Position;
+use crate::{Board, Color};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const GameOutcome: &'static str = r#"
+export type DrawReason =
+    | "threefold_repetition"
+    | "twenty_five_move_rule"
+    | "sixteen_move_rule"
+    | "five_move_rule";
+
+export type GameOutcome =
+    | { kind: "ongoing" }
+    | { kind: "white_wins" }
+    | { kind: "black_wins" }
+    | { kind: "draw", reason: DrawReason };
+"#;
+
+//Plies each rule allows, counted for both sides together
+const KING_MOVES_LIMIT: u32 = 50;
+const SIXTEEN_MOVES_LIMIT: u32 = 32;
+const FIVE_MOVES_LIMIT: u32 = 10;
+
+/// Why a game ended in a draw.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawReason {
+    /// Same position with the same side to move for the third time.
+    ThreefoldRepetition,
+    /// 25 moves each of only kings without a capture.
+    TwentyFiveMoveRule,
+    /// 16 moves each of three figures with a king against a lone king.
+    SixteenMoveRule,
+    /// 5 moves each of at most two figures with a king against a lone king.
+    FiveMoveRule,
+}
+
+/// State of the game as seen by the side to move.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GameOutcome {
+    Ongoing,
+    WhiteWins,
+    BlackWins,
+    Draw { reason: DrawReason },
+}
+
+impl GameOutcome {
+    pub fn is_over(&self) -> bool {
+        *self != GameOutcome::Ongoing
+    }
+
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameOutcome::WhiteWins => Some(Color::White),
+            GameOutcome::BlackWins => Some(Color::Black),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HistoryEntry {
+    key: u64,
+    //Plies of king moves without a capture which led to the position
+    king_plies: u32,
+    //Move limit of the small endgame the position is in, if any
+    endgame: Option<(u32, DrawReason)>,
+    //Plies since the position entered that endgame
+    material_plies: u32,
+}
+
+/// Positions of the game so far, the last one is the current position.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct PositionHistory {
+    entries: Vec<HistoryEntry>,
+}
+
+impl PositionHistory {
+    /// History starting at `position` with the given transposition key.
+    pub(crate) fn new(key: u64, position: &Position) -> Self {
+        PositionHistory {
+            entries: vec![HistoryEntry {
+                key,
+                king_plies: 0,
+                endgame: endgame_limit(position),
+                material_plies: 0,
+            }],
+        }
+    }
+
+    /// Records `position` reached by a move. Man moves and captures can't be undone,
+    /// the endgame counts run until the material makes it a different endgame.
+    pub(crate) fn push(&mut self, key: u64, king_moved: bool, capture: bool, position: &Position) {
+        let endgame = endgame_limit(position);
+        let (king_plies, material_plies) = match self.entries.last() {
+            Some(entry) if entry.endgame == endgame => (entry.king_plies, entry.material_plies + 1),
+            Some(entry) => (entry.king_plies, 0),
+            None => (0, 0),
+        };
+        self.entries.push(HistoryEntry {
+            key,
+            king_plies: if king_moved && !capture {
+                king_plies + 1
+            } else {
+                0
+            },
+            endgame,
+            material_plies,
+        });
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.entries.pop();
+    }
+
+    /// How many times the current position has occurred, itself included.
+    fn repetitions(&self) -> usize {
+        let Some((current, earlier)) = self.entries.split_last() else {
+            return 0;
+        };
+        //Only positions since the last man move or capture can repeat,
+        //and only every other one has the same side to move
+        let reversible = earlier.len().min(current.king_plies as usize);
+        1 + earlier[earlier.len() - reversible..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .filter(|entry| entry.key == current.key)
+            .count()
+    }
+
+    /// Draw rule ending the game in the current position of the history. Search treats
+    /// a position repeated once as a draw, a game needs three occurrences.
+    pub(crate) fn draw_reason(&self, repetitions: usize) -> Option<DrawReason> {
+        let current = self.entries.last()?;
+        if self.repetitions() >= repetitions {
+            return Some(DrawReason::ThreefoldRepetition);
+        }
+        if let Some((limit, reason)) = current.endgame {
+            if current.material_plies >= limit {
+                return Some(reason);
+            }
+        }
+        if current.king_plies >= KING_MOVES_LIMIT {
+            return Some(DrawReason::TwentyFiveMoveRule);
+        }
+        None
+    }
+}
+
+//Move limit of small endgames of a side with a king against a lone king
+fn endgame_limit(position: &Position) -> Option<(u32, DrawReason)> {
+    let count = |figures: u64| {
+        (
+            (figures & position.kings).count_ones(),
+            (figures & !position.kings).count_ones(),
+        )
+    };
+    let (white, black) = (count(position.white), count(position.black));
+    let (kings, men) = match (white, black) {
+        (strong, (1, 0)) | ((1, 0), strong) => strong,
+        _ => return None,
+    };
+    if kings == 0 {
+        return None;
+    }
+    match kings + men {
+        3 => Some((SIXTEEN_MOVES_LIMIT, DrawReason::SixteenMoveRule)),
+        1 | 2 => Some((FIVE_MOVES_LIMIT, DrawReason::FiveMoveRule)),
+        _ => None,
+    }
+}
+
+impl Board<'_> {
+    /// Side to move loses when it has no figure left or none of its figures can move,
+    /// otherwise the game may be drawn by one of the rules kept track of by `history`.
+    pub(crate) fn outcome(&self, color: Color, history: &PositionHistory) -> GameOutcome {
+        if !self.position.has_moves(color) {
+            return match color {
+                Color::White => GameOutcome::BlackWins,
+                Color::Black => GameOutcome::WhiteWins,
+            };
+        }
+        match history.draw_reason(3) {
+            Some(reason) => GameOutcome::Draw { reason },
+            None => GameOutcome::Ongoing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_figure_map, IFigure};
+    use std::collections::HashMap;
+
+    fn figure(color: &str, kind: &str) -> IFigure {
+        IFigure {
+            color: color.to_string(),
+            kind: kind.to_string(),
+        }
+    }
+
+    #[test]
+    fn start_position_is_ongoing() {
+        let mut figure_map = initial_figure_map();
+        let board = Board::new(&mut figure_map);
+        assert_eq!(
+            board.outcome(Color::White, &PositionHistory::default()),
+            GameOutcome::Ongoing
+        );
+        assert_eq!(
+            board.outcome(Color::Black, &PositionHistory::default()),
+            GameOutcome::Ongoing
+        );
+    }
+
+    #[test]
+    fn only_the_stuck_side_to_move_loses() {
+        //Black man on 90 can't move, white man on 50 still can
+        let mut figure_map: HashMap<i32, IFigure> =
+            HashMap::from([(90, figure("black", "man")), (50, figure("white", "man"))]);
+        let board = Board::new(&mut figure_map);
+        assert_eq!(
+            board.outcome(Color::Black, &PositionHistory::default()),
+            GameOutcome::WhiteWins
+        );
+        assert_eq!(
+            board.outcome(Color::White, &PositionHistory::default()),
+            GameOutcome::Ongoing
+        );
+        assert_eq!(
+            board
+                .outcome(Color::Black, &PositionHistory::default())
+                .winner(),
+            Some(Color::White)
+        );
+    }
+
+    #[test]
+    fn side_without_figures_loses() {
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([(45, figure("black", "king"))]);
+        let board = Board::new(&mut figure_map);
+        assert_eq!(
+            board.outcome(Color::White, &PositionHistory::default()),
+            GameOutcome::BlackWins
+        );
+        assert!(board
+            .outcome(Color::White, &PositionHistory::default())
+            .is_over());
+    }
+
+    fn position(figures: &[(i32, &str, &str)]) -> Position {
+        let figure_map: HashMap<i32, IFigure> = figures
+            .iter()
+            .map(|(square_no, color, kind)| (*square_no, figure(color, kind)))
+            .collect();
+        Position::from_figure_map(&figure_map)
+    }
+
+    //King moves between distinct positions
+    fn king_moves(history: &mut PositionHistory, position: &Position, first_key: u64, plies: u32) {
+        for key in first_key..first_key + plies as u64 {
+            history.push(key, true, false, position);
+        }
+    }
+
+    #[test]
+    fn third_occurrence_of_a_position_is_a_draw() {
+        let position = Position::from_figure_map(&initial_figure_map());
+        let mut history = PositionHistory::new(1, &position);
+        for _ in 0..2 {
+            for key in [2, 3, 4, 1] {
+                assert_eq!(history.draw_reason(3), None);
+                history.push(key, true, false, &position);
+            }
+        }
+        assert_eq!(
+            history.draw_reason(3),
+            Some(DrawReason::ThreefoldRepetition)
+        );
+        history.pop();
+        assert_eq!(history.draw_reason(3), None);
+    }
+
+    #[test]
+    fn man_move_ends_the_repeatable_positions() {
+        let position = Position::from_figure_map(&initial_figure_map());
+        let mut history = PositionHistory::new(1, &position);
+        history.push(2, true, false, &position);
+        history.push(1, false, false, &position);
+        assert_eq!(history.draw_reason(2), None);
+        history.push(2, true, false, &position);
+        history.push(1, true, false, &position);
+        assert_eq!(
+            history.draw_reason(2),
+            Some(DrawReason::ThreefoldRepetition)
+        );
+    }
+
+    #[test]
+    fn twenty_five_king_moves_each_are_a_draw() {
+        let position = Position::from_figure_map(&initial_figure_map());
+        let mut history = PositionHistory::new(0, &position);
+        king_moves(&mut history, &position, 1, 49);
+        assert_eq!(history.draw_reason(3), None);
+        history.push(50, true, false, &position);
+        assert_eq!(history.draw_reason(3), Some(DrawReason::TwentyFiveMoveRule));
+        history.push(51, true, true, &position);
+        assert_eq!(history.draw_reason(3), None);
+    }
+
+    #[test]
+    fn three_figures_against_a_king_have_sixteen_moves() {
+        let position = position(&[
+            (3, "white", "king"),
+            (5, "white", "king"),
+            (61, "white", "man"),
+            (96, "black", "king"),
+        ]);
+        let mut history = PositionHistory::new(0, &position);
+        king_moves(&mut history, &position, 1, 31);
+        assert_eq!(history.draw_reason(3), None);
+        history.push(32, false, false, &position);
+        assert_eq!(history.draw_reason(3), Some(DrawReason::SixteenMoveRule));
+    }
+
+    #[test]
+    fn promotion_within_the_endgame_keeps_counting() {
+        let before = position(&[
+            (3, "white", "king"),
+            (5, "white", "king"),
+            (12, "white", "man"),
+            (96, "black", "king"),
+        ]);
+        let promoted = position(&[
+            (3, "white", "king"),
+            (5, "white", "king"),
+            (1, "white", "king"),
+            (96, "black", "king"),
+        ]);
+        let mut history = PositionHistory::new(0, &before);
+        king_moves(&mut history, &before, 1, 20);
+        history.push(21, false, false, &promoted);
+        king_moves(&mut history, &promoted, 22, 10);
+        assert_eq!(history.draw_reason(3), None);
+        history.push(32, true, false, &promoted);
+        assert_eq!(history.draw_reason(3), Some(DrawReason::SixteenMoveRule));
+    }
+
+    #[test]
+    fn two_figures_against_a_king_have_five_moves() {
+        let three_figures = position(&[
+            (3, "white", "king"),
+            (5, "white", "king"),
+            (61, "white", "man"),
+            (96, "black", "king"),
+        ]);
+        let position = position(&[
+            (3, "white", "king"),
+            (61, "white", "man"),
+            (96, "black", "king"),
+        ]);
+        let mut history = PositionHistory::new(0, &three_figures);
+        king_moves(&mut history, &three_figures, 1, 20);
+        //Counting starts again from the capture entering the smaller endgame
+        history.push(21, true, true, &position);
+        king_moves(&mut history, &position, 22, 9);
+        assert_eq!(history.draw_reason(3), None);
+        history.push(31, true, false, &position);
+        assert_eq!(history.draw_reason(3), Some(DrawReason::FiveMoveRule));
+    }
+
+    #[test]
+    fn endgame_limits_need_a_king_against_a_lone_king() {
+        let four_kings = position(&[
+            (1, "white", "king"),
+            (3, "white", "king"),
+            (5, "white", "king"),
+            (7, "white", "king"),
+            (96, "black", "king"),
+        ]);
+        let three_men = position(&[
+            (61, "white", "man"),
+            (63, "white", "man"),
+            (65, "white", "man"),
+            (96, "black", "king"),
+        ]);
+        let king_and_man = position(&[
+            (3, "white", "king"),
+            (61, "white", "man"),
+            (96, "black", "man"),
+        ]);
+        for position in [four_kings, three_men, king_and_man] {
+            let mut history = PositionHistory::new(0, &position);
+            king_moves(&mut history, &position, 1, 32);
+            assert_eq!(history.draw_reason(3), None);
+        }
+    }
+
+    #[test]
+    fn game_repeating_king_moves_is_drawn() {
+        let mut game = crate::Game::from_figure_map(
+            HashMap::from([
+                (1, figure("white", "king")),
+                (3, figure("white", "king")),
+                (61, figure("white", "man")),
+                (63, figure("white", "man")),
+                (38, figure("black", "king")),
+                (98, figure("black", "king")),
+            ]),
+            Color::White,
+        );
+        let king_move = |game: &mut crate::Game, from: i32, to: i32| {
+            let moves = game
+                .legal_moves()
+                .into_iter()
+                .find(|moves| moves[0].moved_figure_no == from && moves[0].square_no == to)
+                .unwrap();
+            game.make_move(moves).unwrap();
+        };
+        for _ in 0..2 {
+            assert_eq!(game.outcome(), GameOutcome::Ongoing);
+            king_move(&mut game, 1, 12);
+            king_move(&mut game, 98, 89);
+            king_move(&mut game, 12, 1);
+            king_move(&mut game, 89, 98);
+        }
+        assert_eq!(
+            game.outcome(),
+            GameOutcome::Draw {
+                reason: DrawReason::ThreefoldRepetition
+            }
+        );
+        assert!(game.undo());
+        assert_eq!(game.outcome(), GameOutcome::Ongoing);
+    }
+}
@@ -0,0 +1,869 @@
+use crate::fen::{from_fen, to_fen};
+use crate::notation::move_notation;
+use crate::{initial_figure_map, Board, Color, FenError, Game, GameOutcome, IFigure, Move};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use wasm_bindgen::prelude::*;
+
+//Move text is wrapped before this column
+const LINE_WIDTH: usize = 80;
+
+#[wasm_bindgen(typescript_custom_section)]
+const PdnGame: &'static str = r#"
+export type PdnResult = "white_wins" | "black_wins" | "draw" | "unknown";
+
+export interface PdnMove {
+    moves: Move[],
+    comment?: string,
+    variations: PdnLine[],
+}
+
+export interface PdnLine {
+    comment?: string,
+    moves: PdnMove[],
+}
+
+export interface PdnGame {
+    tags: [string, string][],
+    line: PdnLine,
+    result: PdnResult,
+}
+
+export interface PdnError extends Error {
+    line: number,
+    column: number,
+}
+"#;
+
+/// Result written at the end of the move text.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PdnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    #[default]
+    Unknown,
+}
+
+impl PdnResult {
+    //Chess style results are accepted as well
+    fn parse(text: &str) -> Option<PdnResult> {
+        match text {
+            "2-0" | "1-0" => Some(PdnResult::WhiteWins),
+            "0-2" | "0-1" => Some(PdnResult::BlackWins),
+            "1-1" | "1/2-1/2" => Some(PdnResult::Draw),
+            "*" => Some(PdnResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn notation(&self) -> &'static str {
+        match self {
+            PdnResult::WhiteWins => "2-0",
+            PdnResult::BlackWins => "0-2",
+            PdnResult::Draw => "1-1",
+            PdnResult::Unknown => "*",
+        }
+    }
+}
+
+impl From<GameOutcome> for PdnResult {
+    fn from(outcome: GameOutcome) -> Self {
+        match outcome {
+            GameOutcome::Ongoing => PdnResult::Unknown,
+            GameOutcome::WhiteWins => PdnResult::WhiteWins,
+            GameOutcome::BlackWins => PdnResult::BlackWins,
+            GameOutcome::Draw { .. } => PdnResult::Draw,
+        }
+    }
+}
+
+/// Move of the move text with the comment written after it and the lines played instead.
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct PdnMove {
+    pub moves: Vec<Move>,
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub variations: Vec<PdnLine>,
+    //Line and column of the move in the parsed text
+    #[serde(skip)]
+    pub(crate) location: (usize, usize),
+}
+
+/// Sequence of moves, the comment is the one written before the first move.
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct PdnLine {
+    pub comment: Option<String>,
+    pub moves: Vec<PdnMove>,
+}
+
+/// Game of a PDN file, moves are checked against the rules while parsing.
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct PdnGame {
+    pub tags: Vec<(String, String)>,
+    pub line: PdnLine,
+    pub result: PdnResult,
+    //Line and column of the FEN tag in the parsed text
+    #[serde(skip)]
+    pub(crate) fen_location: (usize, usize),
+}
+
+impl PdnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Position the game starts from, given by the FEN tag if there is one.
+    pub fn start_position(&self) -> Result<(HashMap<i32, IFigure>, Color), FenError> {
+        match self.tag("FEN") {
+            Some(fen) => from_fen(fen),
+            None => Ok((initial_figure_map(), Color::White)),
+        }
+    }
+
+    /// Moves of the main line.
+    pub fn moves(&self) -> Vec<Vec<Move>> {
+        self.line
+            .moves
+            .iter()
+            .map(|mov| mov.moves.clone())
+            .collect()
+    }
+}
+
+/// Malformed or illegal PDN with the position of the offending text, both counted from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdnError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PdnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for PdnError {}
+
+impl From<PdnError> for JsValue {
+    fn from(error: PdnError) -> Self {
+        let js_error: JsValue = JsError::new(&error.to_string()).into();
+        let _ = js_sys::Reflect::set(&js_error, &"line".into(), &error.line.into());
+        let _ = js_sys::Reflect::set(&js_error, &"column".into(), &error.column.into());
+        js_error
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    MoveNumber,
+    Move(String),
+    Result(PdnResult),
+}
+
+#[derive(Clone, Debug)]
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer<'_> {
+    fn error(&self, message: String) -> PdnError {
+        PdnError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next_char();
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn tokens(mut self) -> Result<Vec<Spanned>, PdnError> {
+        let mut tokens = vec![];
+        loop {
+            self.skip_spaces();
+            let (line, column) = (self.line, self.column);
+            let Some(&c) = self.chars.peek() else {
+                return Ok(tokens);
+            };
+            let token = match c {
+                '[' => {
+                    self.next_char();
+                    Some(self.tag(line, column)?)
+                }
+                '{' => {
+                    self.next_char();
+                    Some(self.comment(line, column)?)
+                }
+                '(' => {
+                    self.next_char();
+                    Some(Token::VariationStart)
+                }
+                ')' => {
+                    self.next_char();
+                    Some(Token::VariationEnd)
+                }
+                _ => {
+                    let word = self.word();
+                    self.classify(&word, line, column, &mut tokens)?;
+                    None
+                }
+            };
+            if let Some(token) = token {
+                tokens.push(Spanned {
+                    token,
+                    line,
+                    column,
+                });
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || "[]{}()".contains(c) {
+                break;
+            }
+            word.push(c);
+            self.next_char();
+        }
+        word
+    }
+
+    //Move numbers may be written right before the move, like `1.32-28`
+    fn classify(
+        &self,
+        word: &str,
+        line: usize,
+        column: usize,
+        tokens: &mut Vec<Spanned>,
+    ) -> Result<(), PdnError> {
+        let spanned = |token, offset| Spanned {
+            token,
+            line,
+            column: column + offset,
+        };
+        if let Some(result) = PdnResult::parse(word) {
+            tokens.push(spanned(Token::Result(result), 0));
+            return Ok(());
+        }
+        //Numeric annotation glyphs like `$1` are skipped
+        if word.starts_with('$') {
+            return Ok(());
+        }
+        let digits = word.chars().take_while(char::is_ascii_digit).count();
+        let mut text = word;
+        if digits > 0 && word[digits..].starts_with('.') {
+            tokens.push(spanned(Token::MoveNumber, 0));
+            text = word[digits..].trim_start_matches('.');
+        }
+        //Strength marks like `!` or `?!` aren't kept
+        let mov = text.trim_end_matches(['!', '?']);
+        if mov.is_empty() {
+            return Ok(());
+        }
+        if !mov
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == 'x')
+        {
+            return Err(PdnError {
+                line,
+                column,
+                message: format!("unexpected \"{word}\""),
+            });
+        }
+        tokens.push(spanned(
+            Token::Move(mov.to_string()),
+            word.len() - text.len(),
+        ));
+        Ok(())
+    }
+
+    //Unclosed tags and comments are reported where they start
+    fn tag(&mut self, line: usize, column: usize) -> Result<Token, PdnError> {
+        self.skip_spaces();
+        let mut name = String::new();
+        while let Some(&c) = self
+            .chars
+            .peek()
+            .filter(|c| c.is_alphanumeric() || **c == '_')
+        {
+            name.push(c);
+            self.next_char();
+        }
+        if name.is_empty() {
+            return Err(self.error("tag name expected".to_string()));
+        }
+        self.skip_spaces();
+        if self.next_char() != Some('"') {
+            return Err(self.error(format!("value of tag {name} has to be quoted")));
+        }
+        let mut value = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') => break,
+                Some('\\') => match self.next_char() {
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                Some(c) => value.push(c),
+                None => {
+                    return Err(PdnError {
+                        line,
+                        column,
+                        message: format!("value of tag {name} is not closed"),
+                    })
+                }
+            }
+        }
+        self.skip_spaces();
+        if self.next_char() != Some(']') {
+            return Err(self.error(format!("tag {name} is not closed with ]")));
+        }
+        Ok(Token::Tag(name, value))
+    }
+
+    fn comment(&mut self, line: usize, column: usize) -> Result<Token, PdnError> {
+        let mut text = String::new();
+        loop {
+            match self.next_char() {
+                Some('}') => return Ok(Token::Comment(text.trim().to_string())),
+                Some(c) => text.push(c),
+                None => {
+                    return Err(PdnError {
+                        line,
+                        column,
+                        message: "comment is not closed with }".to_string(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Spanned> {
+        self.tokens.get(self.next)
+    }
+
+    fn error_at(spanned: &Spanned, message: String) -> PdnError {
+        PdnError {
+            line: spanned.line,
+            column: spanned.column,
+            message,
+        }
+    }
+
+    fn game(&mut self) -> Result<Option<PdnGame>, PdnError> {
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        let mut game = PdnGame::default();
+        let (mut figure_map, mut color) = (initial_figure_map(), Color::White);
+        while let Some(Spanned {
+            token: Token::Tag(name, value),
+            ..
+        }) = self.peek().cloned()
+        {
+            let spanned = &self.tokens[self.next];
+            check_tag(&name, &value).map_err(|message| Parser::error_at(spanned, message))?;
+            if name == "FEN" {
+                (figure_map, color) = from_fen(&value)
+                    .map_err(|error| Parser::error_at(spanned, format!("FEN {error}")))?;
+                game.fen_location = (spanned.line, spanned.column);
+            }
+            game.tags.push((name, value));
+            self.next += 1;
+        }
+        game.line = self.line(&mut figure_map, color, None)?;
+        game.result = match self.peek() {
+            Some(Spanned {
+                token: Token::Result(result),
+                ..
+            }) => {
+                let result = *result;
+                self.next += 1;
+                result
+            }
+            _ => game
+                .tag("Result")
+                .and_then(PdnResult::parse)
+                .unwrap_or_default(),
+        };
+        Ok(Some(game))
+    }
+
+    //Parses moves until the end of the game or of the variation opened at `variation_start`
+    fn line(
+        &mut self,
+        figure_map: &mut HashMap<i32, IFigure>,
+        mut color: Color,
+        variation_start: Option<&Spanned>,
+    ) -> Result<PdnLine, PdnError> {
+        let mut line = PdnLine::default();
+        //Position before the last move, where its variations start from
+        let mut previous: Option<(HashMap<i32, IFigure>, Color)> = None;
+        while let Some(spanned) = self.peek().cloned() {
+            match &spanned.token {
+                Token::Tag(..) | Token::Result(_) => break,
+                Token::VariationEnd => {
+                    if variation_start.is_none() {
+                        return Err(Parser::error_at(&spanned, "unexpected )".to_string()));
+                    }
+                    self.next += 1;
+                    return Ok(line);
+                }
+                Token::MoveNumber => self.next += 1,
+                Token::Comment(text) => {
+                    self.next += 1;
+                    let comment = match line.moves.last_mut() {
+                        Some(mov) => &mut mov.comment,
+                        None => &mut line.comment,
+                    };
+                    append_comment(comment, text);
+                }
+                Token::VariationStart => {
+                    self.next += 1;
+                    let Some((figure_map, color)) = &previous else {
+                        return Err(Parser::error_at(
+                            &spanned,
+                            "variation has to follow the move it replaces".to_string(),
+                        ));
+                    };
+                    let mut figure_map = figure_map.clone();
+                    let variation = self.line(&mut figure_map, *color, Some(&spanned))?;
+                    if let Some(mov) = line.moves.last_mut() {
+                        mov.variations.push(variation);
+                    }
+                }
+                Token::Move(text) => {
+                    self.next += 1;
+                    let moves = Board::new(figure_map)
+                        .notation_move(color, text)
+                        .map_err(|error| Parser::error_at(&spanned, error.to_string()))?;
+                    previous = Some((figure_map.clone(), color));
+                    Board::new(figure_map).make_moves(&moves);
+                    color = color.opponent();
+                    line.moves.push(PdnMove {
+                        moves,
+                        location: (spanned.line, spanned.column),
+                        ..PdnMove::default()
+                    });
+                }
+            }
+        }
+        match variation_start {
+            Some(start) => Err(Parser::error_at(
+                start,
+                "variation is not closed with )".to_string(),
+            )),
+            None => Ok(line),
+        }
+    }
+}
+
+fn check_tag(name: &str, value: &str) -> Result<(), String> {
+    match name {
+        //Game type 20 is international draughts, optionally followed by board details
+        "GameType" if value.split(',').next() != Some("20") => Err(format!(
+            "game type {value} is not international draughts (20)"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn append_comment(comment: &mut Option<String>, text: &str) {
+    match comment {
+        Some(comment) => {
+            comment.push(' ');
+            comment.push_str(text);
+        }
+        None => *comment = Some(text.to_string()),
+    }
+}
+
+/// Games of a PDN file, each starting from the initial position.
+pub fn parse_pdn(text: &str) -> Result<Vec<PdnGame>, PdnError> {
+    let lexer = Lexer {
+        chars: text.chars().peekable(),
+        line: 1,
+        column: 1,
+    };
+    let mut parser = Parser {
+        tokens: lexer.tokens()?,
+        next: 0,
+    };
+    let mut games = vec![];
+    while let Some(game) = parser.game()? {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/// PDN text of the game, move text is wrapped at 80 columns.
+pub fn write_pdn(game: &PdnGame) -> String {
+    let mut text = String::new();
+    for (name, value) in &game.tags {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = writeln!(text, "[{name} \"{value}\"]");
+    }
+    if !game.tags.is_empty() {
+        text.push('\n');
+    }
+    let mut words: Vec<String> = vec![];
+    let color = game
+        .start_position()
+        .map_or(Color::White, |(_, color)| color);
+    write_line(&game.line, color, 1, &mut words);
+    words.push(game.result.notation().to_string());
+
+    let mut line_len = 0;
+    let mut previous: Option<&str> = None;
+    for word in &words {
+        let glued = previous == Some("(") || word == ")";
+        if previous.is_some() && !glued {
+            if line_len + 1 + word.len() > LINE_WIDTH {
+                text.push('\n');
+                line_len = 0;
+            } else {
+                text.push(' ');
+                line_len += 1;
+            }
+        }
+        text.push_str(word);
+        line_len += word.len();
+        previous = Some(word);
+    }
+    text.push('\n');
+    text
+}
+
+fn write_line(line: &PdnLine, mut color: Color, mut move_no: u32, words: &mut Vec<String>) {
+    if let Some(comment) = &line.comment {
+        words.push(format!("{{{comment}}}"));
+    }
+    //Black move needs its number after anything written between it and the white move
+    let mut number_needed = true;
+    for mov in &line.moves {
+        match color {
+            Color::White => words.push(format!("{move_no}.")),
+            Color::Black if number_needed => words.push(format!("{move_no}...")),
+            Color::Black => {}
+        }
+        words.push(move_notation(&mov.moves));
+        number_needed = mov.comment.is_some() || !mov.variations.is_empty();
+        if let Some(comment) = &mov.comment {
+            words.push(format!("{{{comment}}}"));
+        }
+        for variation in &mov.variations {
+            words.push("(".to_string());
+            write_line(variation, color, move_no, words);
+            words.push(")".to_string());
+        }
+        if color == Color::Black {
+            move_no += 1;
+        }
+        color = color.opponent();
+    }
+}
+
+#[wasm_bindgen]
+impl Game {
+    /// Game read from the first game of a PDN file, variations are left out.
+    pub fn from_pdn(text: &str) -> Result<Game, PdnError> {
+        let no_game = PdnError {
+            line: 1,
+            column: 1,
+            message: "there is no game in the text".to_string(),
+        };
+        let pdn_game = parse_pdn(text)?.into_iter().next().ok_or(no_game)?;
+        Game::from_pdn_game(pdn_game)
+    }
+
+    /// PDN text of the played moves, with the result if the game is over. Games which
+    /// didn't start from the initial position get a FEN tag.
+    pub fn to_pdn(&mut self) -> String {
+        let result = PdnResult::from(self.outcome());
+        let mut tags = vec![
+            ("GameType".to_string(), "20".to_string()),
+            ("Result".to_string(), result.notation().to_string()),
+        ];
+        let mut start = self.figure_map().clone();
+        let mut board = Board::new(&mut start);
+        for moves in self.history().iter().rev() {
+            board.unmake_moves(moves);
+        }
+        let start_side = match self.history().len() % 2 {
+            0 => self.side_to_move(),
+            _ => self.side_to_move().opponent(),
+        };
+        let start_fen = to_fen(&start, start_side);
+        if start_fen != to_fen(&initial_figure_map(), Color::White) {
+            tags.push(("FEN".to_string(), start_fen));
+        }
+        let pdn_game = PdnGame {
+            tags,
+            line: PdnLine {
+                comment: None,
+                moves: self
+                    .history()
+                    .iter()
+                    .map(|moves| PdnMove {
+                        moves: moves.clone(),
+                        ..PdnMove::default()
+                    })
+                    .collect(),
+            },
+            result,
+            ..PdnGame::default()
+        };
+        write_pdn(&pdn_game)
+    }
+}
+
+impl Game {
+    //Replays the main line of a parsed game, errors point at the FEN tag or the move
+    pub(crate) fn from_pdn_game(pdn_game: PdnGame) -> Result<Game, PdnError> {
+        let error_at = |(line, column), message| PdnError {
+            line,
+            column,
+            message,
+        };
+        let (figure_map, side_to_move) = pdn_game
+            .start_position()
+            .map_err(|error| error_at(pdn_game.fen_location, error.to_string()))?;
+        let mut game = Game::from_figure_map(figure_map, side_to_move);
+        for mov in pdn_game.line.moves {
+            //Moves were checked while parsing, an error means the rules disagree
+            game.make_move(mov.moves)
+                .map_err(|error| error_at(mov.location, error.to_string()))?;
+        }
+        Ok(game)
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const parse_pdn: &'static str = r#"
+/** @throws {PdnError} */
+export function parse_pdn(text: string): PdnGame[];
+export function write_pdn(game: PdnGame): string;
+"#;
+
+#[wasm_bindgen(js_name = parse_pdn, skip_typescript)]
+pub fn parse_pdn_js(text: &str) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&parse_pdn(text)?)?)
+}
+
+#[wasm_bindgen(js_name = write_pdn, skip_typescript)]
+pub fn write_pdn_js(game: JsValue) -> Result<String, JsError> {
+    let game: PdnGame = serde_wasm_bindgen::from_value(game)?;
+    Ok(write_pdn(&game))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::square_number;
+
+    fn notation(game: &PdnGame) -> Vec<String> {
+        game.line
+            .moves
+            .iter()
+            .map(|mov| move_notation(&mov.moves))
+            .collect()
+    }
+
+    #[test]
+    fn reads_tags_moves_comments_and_result() {
+        let text = r#"[Event "Club \"Damiera\" championship"]
+[White "Anna"]
+[Black "Jan"]
+[GameType "20"]
+
+{Opening} 1. 32-28 {solid} 19-23 2.28x19 14x23 3. 37-32! $1 10-14
+4. 41-37 * "#;
+        let games = parse_pdn(text).unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+        assert_eq!(game.tag("Event"), Some("Club \"Damiera\" championship"));
+        assert_eq!(game.tag("Black"), Some("Jan"));
+        assert_eq!(game.line.comment.as_deref(), Some("Opening"));
+        assert_eq!(game.line.moves[0].comment.as_deref(), Some("solid"));
+        assert_eq!(
+            notation(game),
+            vec!["32-28", "19-23", "28x19", "14x23", "37-32", "10-14", "41-37"]
+        );
+        assert_eq!(game.result, PdnResult::Unknown);
+        let captured = game.line.moves[2].moves[0].captured_figure_no.unwrap();
+        assert_eq!(square_number(captured), Some(23));
+    }
+
+    #[test]
+    fn variations_start_from_the_position_before_the_move() {
+        let text =
+            "1. 32-28 18-22 (1... 19-24 2. 34-30 {or} (2. 31-27 14-19) 13-19) 2. 37-32 12-18 2-0";
+        let game = &parse_pdn(text).unwrap()[0];
+        assert_eq!(notation(game), vec!["32-28", "18-22", "37-32", "12-18"]);
+        let variation = &game.line.moves[1].variations[0];
+        assert_eq!(variation.moves.len(), 3);
+        assert_eq!(variation.moves[1].comment.as_deref(), Some("or"));
+        assert_eq!(variation.moves[1].variations[0].moves.len(), 2);
+        assert_eq!(game.result, PdnResult::WhiteWins);
+    }
+
+    #[test]
+    fn written_game_reads_back_the_same() {
+        let text = "1. 32-28 18-22 (1... 19-24 2. 34-30 {or} (2. 31-27 14-19) 13-19) 2. 37-32 12-18
+            3. 41-37 7-12 4. 46-41 1-7 5. 31-26 17-21 6. 26x17 12x21 1-1";
+        let game = &parse_pdn(text).unwrap()[0];
+        let mut tagged = game.clone();
+        tagged
+            .tags
+            .push(("Event".to_string(), "Back \"slash\\\"".to_string()));
+        let written = write_pdn(&tagged);
+        assert!(
+            written.starts_with("[Event \"Back \\\"slash\\\\\\\"\"]\n\n1. 32-28 18-22 (1... 19-24")
+        );
+        assert!(written.contains("(1... 19-24 2. 34-30 {or} (2. 31-27 14-19) 2... 13-19) 2. 37-32"));
+        assert!(written.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert!(written.lines().count() > 3);
+        let read = &parse_pdn(&written).unwrap()[0];
+        assert_eq!(read.tags, tagged.tags);
+        assert_eq!(notation(read), notation(game));
+        assert_eq!(read.result, PdnResult::Draw);
+        assert_eq!(write_pdn(read), written);
+    }
+
+    #[test]
+    fn several_games_are_read() {
+        let text = "[Round \"1\"]\n1. 32-28 2-0\n\n[Round \"2\"]\n1. 31-27 18-22 0-2\n";
+        let games = parse_pdn(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].tag("Round"), Some("2"));
+        assert_eq!(games[1].result, PdnResult::BlackWins);
+        assert_eq!(notation(&games[1]), vec!["31-27", "18-22"]);
+    }
+
+    #[test]
+    fn illegal_moves_are_reported_where_they_are_written() {
+        let error = parse_pdn("1. 32-28 18-22\n2. 37-32 22-26").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 10));
+        assert!(error.message.contains("22-26 is illegal"));
+
+        let error = parse_pdn("1. 32-28 19-23\n2. 28-22").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 4));
+        assert!(error.message.contains("capture is mandatory"));
+
+        let error = parse_pdn("1. 19-23").unwrap_err();
+        assert!(error.message.contains("player who is not on move"));
+
+        let error = parse_pdn("1. 32-28 {never closed").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 10));
+
+        let error = parse_pdn("1. 32-28 (31-27").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 10));
+
+        let error = parse_pdn("[GameType \"21\"]\n1. 32-28").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 1));
+
+        let error = parse_pdn("1. 32-28 19-23 2. e3-d4").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 19));
+    }
+
+    #[test]
+    fn game_starts_from_the_fen_tag() {
+        let text = "[FEN \"B:W28,K50:B19,20\"]\n1... 19-23 2. 28x19 14x23";
+        let error = parse_pdn(text).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 21));
+        let text = "[FEN \"B:W28,K50:B19,20\"]\n1... 19-23 2. 28x19 20-24 *";
+        let game = &parse_pdn(text).unwrap()[0];
+        assert_eq!(notation(game), vec!["19-23", "28x19", "20-24"]);
+        assert!(write_pdn(game).contains("\n\n1... 19-23 2. 28x19 20-24 *"));
+
+        let mut game = Game::from_pdn(text).unwrap();
+        assert_eq!(game.to_fen(), "W:W19,K50:B24");
+        let written = game.to_pdn();
+        assert!(written.contains("[FEN \"B:W28,K50:B19,20\"]"));
+        assert_eq!(Game::from_pdn(&written).unwrap().to_fen(), "W:W19,K50:B24");
+
+        let error = parse_pdn("[Event \"x\"]\n[FEN \"W:W3:B1\"]").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+        assert!(error.message.contains("should have become a king"));
+    }
+
+    #[test]
+    fn game_round_trips_through_pdn() {
+        let mut game = Game::from_pdn("1. 32-28 19-23 2. 28x19 14x23 *").unwrap();
+        assert_eq!(game.history().len(), 4);
+        assert_eq!(game.side_to_move(), Color::White);
+        let text = game.to_pdn();
+        assert_eq!(
+            text,
+            "[GameType \"20\"]\n[Result \"*\"]\n\n1. 32-28 19-23 2. 28x19 14x23 *\n"
+        );
+        let read = Game::from_pdn(&text).unwrap();
+        assert_eq!(read.history().len(), 4);
+        assert!(Game::from_pdn("").is_err());
+    }
+
+    #[test]
+    fn replay_errors_point_at_the_offending_text() {
+        let text = "[Event \"x\"]\n[FEN \"W:W32:B19\"]\n1. 32-28 19-23 *";
+        let mut pdn_game = parse_pdn(text).unwrap().remove(0);
+        //Black move left with white to move
+        pdn_game.line.moves.remove(0);
+        let Err(error) = Game::from_pdn_game(pdn_game.clone()) else {
+            panic!("black move was replayed for white");
+        };
+        assert_eq!((error.line, error.column), (3, 10));
+        pdn_game.tags[1].1 = "W:W3:B1".to_string();
+        let Err(error) = Game::from_pdn_game(pdn_game) else {
+            panic!("invalid FEN was set up");
+        };
+        assert_eq!((error.line, error.column), (2, 1));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::bitboard::BitMove;
+use crate::validation::figure_map_from_js;
+use crate::{Board, Color, IFigure, Move};
+use serde::Serialize;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const PerftDivision: &'static str = r#"
+export interface PerftDivision {
+    moves: Move[],
+    nodes: number,
+}
+"#;
+
+/// Leaf count below a single root move.
+#[derive(Clone, Serialize, Debug)]
+pub struct PerftDivision {
+    pub moves: Vec<Move>,
+    pub nodes: u64,
+}
+
+impl Board<'_> {
+    /// Number of move sequences `depth` plies long. Capture sequences taking the same
+    /// figures between the same squares are one move, however the figures are jumped.
+    pub(crate) fn perft(&mut self, depth: u32, color: Color) -> u64 {
+        let mut move_lists: Vec<Vec<BitMove>> = vec![vec![]; depth as usize];
+        self.count_leaves(color, &mut move_lists)
+    }
+
+    //One move list per remaining ply
+    fn count_leaves(&mut self, color: Color, move_lists: &mut [Vec<BitMove>]) -> u64 {
+        let Some((moves_vector, deeper_lists)) = move_lists.split_first_mut() else {
+            return 1;
+        };
+        self.get_available_moves(&color, moves_vector);
+        if deeper_lists.is_empty() {
+            return moves_vector.len() as u64;
+        }
+        let mut nodes = 0;
+        for &mov in moves_vector.iter() {
+            let undo = self.make(mov);
+            nodes += self.count_leaves(color.opponent(), deeper_lists);
+            self.unmake(undo);
+        }
+        nodes
+    }
+
+    /// Perft split by root move, in generation order.
+    pub(crate) fn perft_divide(&mut self, depth: u32, color: Color) -> Vec<PerftDivision> {
+        if depth == 0 {
+            return vec![];
+        }
+        let mut root_moves: Vec<BitMove> = vec![];
+        self.get_available_moves(&color, &mut root_moves);
+        root_moves
+            .into_iter()
+            .map(|mov| {
+                let moves = self.to_moves(mov);
+                let undo = self.make(mov);
+                let nodes = self.perft(depth - 1, color.opponent());
+                self.unmake(undo);
+                PerftDivision { moves, nodes }
+            })
+            .collect()
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const perft: &'static str = r#"
+export function perft(color: Color, figure_map: Map<number, IFigure>, depth: number): number;
+"#;
+
+#[wasm_bindgen(skip_typescript)]
+pub fn perft(color: Color, figure_map: JsValue, depth: u32) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+    let mut board: Board = Board::new(&mut figure_map);
+    Ok(serde_wasm_bindgen::to_value(&board.perft(depth, color))?)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const perft_divide: &'static str = r#"
+export function perft_divide(color: Color, figure_map: Map<number, IFigure>, depth: number): PerftDivision[];
+"#;
+
+#[wasm_bindgen(skip_typescript)]
+pub fn perft_divide(color: Color, figure_map: JsValue, depth: u32) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+    let mut board: Board = Board::new(&mut figure_map);
+    Ok(serde_wasm_bindgen::to_value(
+        &board.perft_divide(depth, color),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initial_figure_map;
+
+    fn figure(color: &str, kind: &str) -> IFigure {
+        IFigure {
+            color: color.to_string(),
+            kind: kind.to_string(),
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        //Published international draughts perft counts
+        let expected: [u64; 7] = [1, 9, 81, 658, 4265, 27117, 167140];
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(board.perft(depth as u32, Color::White), *nodes);
+        }
+        assert_eq!(board.figure_map.len(), 40);
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        let divisions = board.perft_divide(3, Color::White);
+        assert_eq!(divisions.len(), 9);
+        let total: u64 = divisions.iter().map(|division| division.nodes).sum();
+        assert_eq!(total, board.perft(3, Color::White));
+    }
+
+    #[test]
+    fn man_takes_the_longer_capture() {
+        //63x45 takes one figure, 63x41x23 takes two
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (63, figure("white", "man")),
+            (54, figure("black", "man")),
+            (52, figure("black", "man")),
+            (32, figure("black", "man")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let divisions = board.perft_divide(1, Color::White);
+        assert_eq!(divisions.len(), 1);
+        let squares: Vec<i32> = divisions[0].moves.iter().map(|mov| mov.square_no).collect();
+        assert_eq!(squares, vec![41, 23]);
+    }
+
+    #[test]
+    fn round_trip_capture_counts_once() {
+        //63x45x23x41x63 and 63x41x23x45x63 take the same four figures, so they are one move
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (63, figure("white", "man")),
+            (54, figure("black", "man")),
+            (52, figure("black", "man")),
+            (34, figure("black", "man")),
+            (32, figure("black", "man")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        assert_eq!(board.perft(1, Color::White), 1);
+        let moves = board.legal_moves(&Color::White);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].len(), 4);
+        assert_eq!(moves[0].last().unwrap().square_no, 63);
+        //Black has nothing left to move
+        assert_eq!(board.perft(2, Color::White), 0);
+    }
+
+    #[test]
+    fn king_lands_on_any_square_behind_the_captured_figure() {
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (90, figure("white", "king")),
+            (72, figure("black", "man")),
+            (5, figure("black", "man")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        //Landings on 63, 54, 45, 36, 27, 18 and 9
+        assert_eq!(board.perft(1, Color::White), 7);
+        //Black man on 5 then moves to 14 or 16 after every landing
+        let divisions = board.perft_divide(2, Color::White);
+        let total: u64 = divisions.iter().map(|division| division.nodes).sum();
+        assert_eq!(total, 7 * 2);
+    }
+
+    #[test]
+    fn king_lands_where_the_capture_continues() {
+        //Only landing on 27 lets the king take 38 as well
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (90, figure("white", "king")),
+            (72, figure("black", "man")),
+            (38, figure("black", "man")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let divisions = board.perft_divide(1, Color::White);
+        assert_eq!(divisions.len(), 1);
+        let squares: Vec<i32> = divisions[0].moves.iter().map(|mov| mov.square_no).collect();
+        assert_eq!(squares, vec![27, 49]);
+    }
+}
@@ -0,0 +1,830 @@
+use crate::bitboard::{BitMove, Position};
+use crate::outcome::PositionHistory;
+use crate::validation::figure_map_from_js;
+use crate::{
+    transposition, Board, Color, EvalWeights, IFigure, Move, TranspositionTable, Undo,
+    TRANSPOSITION_TABLE,
+};
+use instant::{Duration, Instant};
+use serde::Serialize;
+use std::cmp;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use wasm_bindgen::prelude::*;
+
+pub(crate) const MAX_SEARCH_DEPTH: i32 = 50;
+//Plies of forced captures resolved after the search depth is reached
+pub(crate) const QUIESCENCE_DEPTH: i32 = 8;
+//Score of a won position, reduced by the plies needed to reach it
+pub(crate) const WIN_SCORE: i32 = 100_000;
+//Scores beyond this are wins counted in plies, no evaluation comes close
+const WIN_THRESHOLD: i32 = WIN_SCORE - 1_000;
+//Clock is read once per this many nodes
+const NODES_PER_TIME_CHECK: u64 = 1024;
+
+//Move ordering scores, history scores stay below killer moves
+const HASH_MOVE_SCORE: i32 = 1_000_000;
+const CAPTURE_SCORE: i32 = 900_000;
+const PROMOTION_SCORE: i32 = 800_000;
+const KILLER_MOVE_SCORE: i32 = 700_000;
+
+#[wasm_bindgen(typescript_custom_section)]
+const SearchResult: &'static str = r#"
+export interface SearchResult {
+    best_move: Move[],
+    pv: Move[][],
+    score: number,
+    depth: number,
+    nodes: number,
+    nps: number,
+}
+"#;
+
+/// Best move of the deepest fully searched iteration with the line expected to follow.
+#[derive(Clone, Default, Serialize, Debug)]
+pub struct SearchResult {
+    best_move: Vec<Move>,
+    //Principal variation, the best move followed by the best replies
+    pv: Vec<Vec<Move>>,
+    //From white's point of view, a man is worth 100
+    score: i32,
+    depth: i32,
+    nodes: u64,
+    nps: u64,
+}
+
+impl SearchResult {
+    pub fn best_move(&self) -> &[Move] {
+        &self.best_move
+    }
+
+    pub fn pv(&self) -> &[Vec<Move>] {
+        &self.pv
+    }
+
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn depth(&self) -> i32 {
+        self.depth
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn nps(&self) -> u64 {
+        self.nps
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const AnalysisLine: &'static str = r#"
+export interface AnalysisLine {
+    score: number,
+    pv: Move[][],
+}
+"#;
+
+/// One root move of a multi-PV search, the move is the first of the variation.
+#[derive(Clone, Serialize, Debug)]
+pub struct AnalysisLine {
+    //From white's point of view, like the score of SearchResult
+    score: i32,
+    pv: Vec<Vec<Move>>,
+}
+
+impl AnalysisLine {
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn pv(&self) -> &[Vec<Move>] {
+        &self.pv
+    }
+}
+
+pub(crate) type IterationReport<'t> = Box<dyn FnMut(&SearchResult) + 't>;
+
+/// State shared by all nodes of one search.
+pub(crate) struct Search<'t> {
+    pub(crate) tt: &'t mut TranspositionTable,
+    pub(crate) weights: EvalWeights,
+    pub(crate) quiescence_depth: i32,
+    pub(crate) move_ordering: bool,
+    //Distance from the root of the node being searched
+    pub(crate) ply: usize,
+    //Two quiet moves per ply which caused a cutoff
+    killers: Vec<[Option<BitMove>; 2]>,
+    //Cutoff score of quiet moves indexed by from * 64 + to
+    history: Vec<i32>,
+    //Move list buffer per ply, kept between nodes so generation doesn't allocate
+    move_lists: Vec<Vec<BitMove>>,
+    //Positions played before the root followed by the searched line, for the draw rules
+    pub(crate) positions: PositionHistory,
+    //Set from outside to end the search early, like the deadline only after the first iteration
+    pub(crate) stop_signal: Option<&'t AtomicBool>,
+    //Called with the result of every completed iteration
+    pub(crate) on_iteration: Option<IterationReport<'t>>,
+    //Draws found by the history of the searched line, values depending on them can't be stored
+    pub(crate) history_draws: u64,
+    started: Instant,
+    deadline: Option<Instant>,
+    nodes: u64,
+    stopped: bool,
+}
+
+impl<'t> Search<'t> {
+    pub(crate) fn new(tt: &'t mut TranspositionTable) -> Self {
+        Search {
+            tt,
+            weights: EvalWeights::default(),
+            quiescence_depth: QUIESCENCE_DEPTH,
+            move_ordering: true,
+            ply: 0,
+            killers: vec![[None; 2]; MAX_SEARCH_DEPTH as usize + 1],
+            history: vec![0; 64 * 64],
+            move_lists: vec![vec![]; (MAX_SEARCH_DEPTH + QUIESCENCE_DEPTH) as usize + 1],
+            positions: PositionHistory::default(),
+            stop_signal: None,
+            on_iteration: None,
+            history_draws: 0,
+            started: Instant::now(),
+            deadline: None,
+            nodes: 0,
+            stopped: false,
+        }
+    }
+
+    /// Called once per node, tells if the search has run out of time.
+    pub(crate) fn should_stop(&mut self) -> bool {
+        self.nodes += 1;
+        if !self.stopped && self.nodes.is_multiple_of(NODES_PER_TIME_CHECK) {
+            self.stopped = self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline || self.stop_requested());
+        }
+        self.stopped
+    }
+
+    /// Win scores counted from the current node instead of the root, as kept in the table.
+    pub(crate) fn value_to_tt(&self, value: i32) -> i32 {
+        match value {
+            value if value >= WIN_THRESHOLD => value + self.ply as i32,
+            value if value <= -WIN_THRESHOLD => value - self.ply as i32,
+            value => value,
+        }
+    }
+
+    /// Table value as a score counted from the root, undoing `value_to_tt`.
+    pub(crate) fn value_from_tt(&self, value: i32) -> i32 {
+        match value {
+            value if value >= WIN_THRESHOLD => value - self.ply as i32,
+            value if value <= -WIN_THRESHOLD => value + self.ply as i32,
+            value => value,
+        }
+    }
+
+    fn stop_requested(&self) -> bool {
+        self.stop_signal
+            .is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub(crate) fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    fn nps(&self) -> u64 {
+        let seconds = self.started.elapsed().as_secs_f64();
+        if seconds > 0.0 {
+            (self.nodes as f64 / seconds) as u64
+        } else {
+            0
+        }
+    }
+
+    /// Empty move list of the current ply, has to be given back with `put_move_list`.
+    pub(crate) fn take_move_list(&mut self) -> Vec<BitMove> {
+        if self.move_lists.len() <= self.ply {
+            self.move_lists.resize_with(self.ply + 1, Vec::new);
+        }
+        mem::take(&mut self.move_lists[self.ply])
+    }
+
+    pub(crate) fn put_move_list(&mut self, moves: Vec<BitMove>) {
+        self.move_lists[self.ply] = moves;
+    }
+
+    /// Sorts moves so the ones most likely to cause a cutoff are searched first.
+    pub(crate) fn order_moves(
+        &self,
+        moves: &mut [BitMove],
+        hash_move: Option<BitMove>,
+        position: &Position,
+    ) {
+        if self.move_ordering {
+            //Stable sort keeps generation order between equally scored moves
+            moves.sort_by_key(|mov| cmp::Reverse(self.move_score(*mov, hash_move, position)));
+        }
+    }
+
+    fn move_score(&self, mov: BitMove, hash_move: Option<BitMove>, position: &Position) -> i32 {
+        if hash_move == Some(mov) {
+            return HASH_MOVE_SCORE;
+        }
+        if mov.is_capture() {
+            let captured_kings = (mov.captured & position.kings).count_ones() as i32;
+            return CAPTURE_SCORE + captured_kings;
+        }
+        if mov.promoted {
+            return PROMOTION_SCORE;
+        }
+        match self.killers.get(self.ply) {
+            Some([Some(killer), _]) if *killer == mov => KILLER_MOVE_SCORE,
+            Some([_, Some(killer)]) if *killer == mov => KILLER_MOVE_SCORE - 1,
+            _ => cmp::min(self.history[history_index(mov)], KILLER_MOVE_SCORE - 2),
+        }
+    }
+
+    /// Remembers a quiet move which refuted the position as a killer and in the history.
+    pub(crate) fn record_cutoff(&mut self, mov: BitMove, depth: i32) {
+        if mov.is_capture() {
+            return;
+        }
+        if let Some(killers) = self.killers.get_mut(self.ply) {
+            if killers[0] != Some(mov) {
+                killers[1] = killers[0];
+                killers[0] = Some(mov);
+            }
+        }
+        let score = &mut self.history[history_index(mov)];
+        *score = score.saturating_add(depth * depth);
+    }
+}
+
+fn history_index(mov: BitMove) -> usize {
+    usize::from(mov.from) * 64 + usize::from(mov.to)
+}
+
+impl Board<'_> {
+    /// Makes a move of the searched line, recording the reached position for the draw rules.
+    pub(crate) fn make_searched(
+        &mut self,
+        search: &mut Search,
+        mov: BitMove,
+        color: Color,
+    ) -> Undo {
+        let king_moved = self.position.is_king(mov.from.into());
+        let undo = self.make(mov);
+        let key = transposition::position_key(self.hash, &color.opponent());
+        search
+            .positions
+            .push(key, king_moved, mov.is_capture(), &self.position);
+        undo
+    }
+
+    pub(crate) fn unmake_searched(&mut self, search: &mut Search, undo: Undo) {
+        search.positions.pop();
+        self.unmake(undo);
+    }
+
+    /// Plays out pending captures before evaluating, so exchanges aren't cut in the middle.
+    pub(crate) fn quiescence(
+        &mut self,
+        search: &mut Search,
+        alpha: i32,
+        beta: i32,
+        color: Color,
+        extension: i32,
+    ) -> i32 {
+        if extension >= search.quiescence_depth || search.should_stop() {
+            return self.evaluate(&search.weights);
+        }
+        //Capture is mandatory, so there is no standing pat when one is available
+        let mut forced_moves = search.take_move_list();
+        self.get_forced_moves(&color, &mut forced_moves);
+        if forced_moves.is_empty() {
+            search.put_move_list(forced_moves);
+            return self.evaluate(&search.weights);
+        }
+
+        let (mut alpha, mut beta) = (alpha, beta);
+        let mut bestval = match color {
+            Color::White => i32::MIN,
+            Color::Black => i32::MAX,
+        };
+        for &mov in &forced_moves {
+            let undo = self.make(mov);
+            search.ply += 1;
+            let value = self.quiescence(search, alpha, beta, color.opponent(), extension + 1);
+            search.ply -= 1;
+            self.unmake(undo);
+            match color {
+                Color::White => {
+                    bestval = cmp::max(bestval, value);
+                    alpha = cmp::max(alpha, bestval);
+                }
+                Color::Black => {
+                    bestval = cmp::min(bestval, value);
+                    beta = cmp::min(beta, bestval);
+                }
+            }
+            if beta <= alpha || search.stopped() {
+                break;
+            }
+        }
+        search.put_move_list(forced_moves);
+        bestval
+    }
+
+    /// Searches one ply deeper each iteration until the time budget or `max_depth` is reached.
+    pub(crate) fn iterative_deepening(
+        &mut self,
+        search: &mut Search,
+        time_budget: Duration,
+        max_depth: i32,
+        color: Color,
+    ) -> SearchResult {
+        let deadline = Instant::now() + time_budget;
+        let (mut best_move, mut score, mut completed_depth) = (None, 0, 0);
+        for depth in 1..=max_depth {
+            let (value, mov) = self.minimax(search, depth, i32::MIN, i32::MAX, color);
+            if search.stopped() {
+                break;
+            }
+            (best_move, score, completed_depth) = (mov, value, depth);
+            if let Some(mut on_iteration) = search.on_iteration.take() {
+                on_iteration(&self.search_result(search, best_move, score, depth, color));
+                search.on_iteration = Some(on_iteration);
+            }
+            if Instant::now() >= deadline || search.stop_requested() {
+                break;
+            }
+            //First iteration always completes, so there is a move to return
+            search.deadline = Some(deadline);
+        }
+        self.search_result(search, best_move, score, completed_depth, color)
+    }
+
+    /// Line starting with `first_move`, followed through the transposition table
+    /// as long as its moves are legal.
+    fn principal_variation(
+        &mut self,
+        search: &Search,
+        first_move: Option<BitMove>,
+        depth: i32,
+        color: Color,
+    ) -> Vec<Vec<Move>> {
+        let mut pv: Vec<Vec<Move>> = vec![];
+        let mut legal_moves: Vec<BitMove> = vec![];
+        let (mut next_move, mut color) = (first_move, color);
+        while let Some(mov) = next_move.filter(|_| pv.len() < depth as usize) {
+            self.get_available_moves(&color, &mut legal_moves);
+            if !legal_moves.contains(&mov) {
+                break;
+            }
+            let moves = self.to_moves(mov);
+            self.make_moves(&moves);
+            pv.push(moves);
+            color = color.opponent();
+            let key = transposition::position_key(self.hash, &color);
+            next_move = search.tt.probe(key).and_then(|entry| entry.best_move);
+        }
+        for moves in pv.iter().rev() {
+            self.unmake_moves(moves);
+        }
+        pv
+    }
+
+    /// Result of a finished search with its principal variation.
+    pub(crate) fn search_result(
+        &mut self,
+        search: &Search,
+        best_move: Option<BitMove>,
+        score: i32,
+        depth: i32,
+        color: Color,
+    ) -> SearchResult {
+        let pv = self.principal_variation(search, best_move, depth, color);
+        SearchResult {
+            best_move: pv.first().cloned().unwrap_or_default(),
+            pv,
+            score,
+            depth,
+            nodes: search.nodes(),
+            nps: search.nps(),
+        }
+    }
+
+    /// Searches every root move with a full window, so each gets an exact score.
+    /// Returns the depth of the last completed iteration and the moves, best first for `color`.
+    pub(crate) fn score_root_moves(
+        &mut self,
+        search: &mut Search,
+        time_budget: Duration,
+        max_depth: i32,
+        color: Color,
+    ) -> (i32, Vec<(i32, BitMove)>) {
+        let deadline = Instant::now() + time_budget;
+        let mut root_moves: Vec<BitMove> = vec![];
+        self.get_available_moves(&color, &mut root_moves);
+        let mut result = (0, vec![]);
+        for depth in 1..=max_depth {
+            let mut scored_moves: Vec<(i32, BitMove)> = vec![];
+            for &mov in &root_moves {
+                let undo = self.make_searched(search, mov, color);
+                search.ply += 1;
+                let (value, _) =
+                    self.minimax(search, depth - 1, i32::MIN, i32::MAX, color.opponent());
+                search.ply -= 1;
+                self.unmake_searched(search, undo);
+                if search.stopped() {
+                    break;
+                }
+                scored_moves.push((value, mov));
+            }
+            if search.stopped() {
+                break;
+            }
+            match color {
+                Color::White => scored_moves.sort_by_key(|(value, _)| cmp::Reverse(*value)),
+                Color::Black => scored_moves.sort_by_key(|(value, _)| *value),
+            }
+            result = (depth, scored_moves);
+            if Instant::now() >= deadline {
+                break;
+            }
+            search.deadline = Some(deadline);
+        }
+        result
+    }
+}
+
+impl Board<'_> {
+    /// The `lines_no` best root moves of `color` with their scores and principal variations.
+    pub(crate) fn multi_pv(
+        &mut self,
+        search: &mut Search,
+        time_budget: Duration,
+        lines_no: usize,
+        color: Color,
+    ) -> Vec<AnalysisLine> {
+        let (depth, scored_moves) =
+            self.score_root_moves(search, time_budget, MAX_SEARCH_DEPTH, color);
+        scored_moves
+            .into_iter()
+            .take(lines_no)
+            .map(|(score, mov)| AnalysisLine {
+                score,
+                pv: self.principal_variation(search, Some(mov), depth, color),
+            })
+            .collect()
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const analyze: &'static str = r#"
+export function analyze(color: Color, figure_map: Map<number, IFigure>, lines_no: number, time_ms: number): AnalysisLine[];
+"#;
+
+#[wasm_bindgen(skip_typescript)]
+pub fn analyze(
+    color: Color,
+    figure_map: JsValue,
+    lines_no: usize,
+    time_ms: u32,
+) -> Result<JsValue, JsError> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsError>(figure_map)?;
+    let mut board: Board = Board::new(&mut figure_map);
+    let lines = TRANSPOSITION_TABLE.with(|tt| {
+        let mut tt = tt.borrow_mut();
+        let mut search = Search::new(&mut tt);
+        board.multi_pv(
+            &mut search,
+            Duration::from_millis(time_ms.into()),
+            lines_no,
+            color,
+        )
+    });
+    Ok(serde_wasm_bindgen::to_value(&lines)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_figure_map, Game};
+
+    fn figure(color: &str) -> IFigure {
+        IFigure {
+            color: color.to_string(),
+            kind: "man".to_string(),
+        }
+    }
+
+    #[test]
+    fn quiescence_plays_out_recapture() {
+        //White has to take 54 landing on 45, then black takes back with 36x54
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (63, figure("white")),
+            (90, figure("white")),
+            (54, figure("black")),
+            (36, figure("black")),
+            (27, figure("black")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        search.quiescence_depth = 0;
+        let (horizon_value, _) = board.minimax(&mut search, 1, i32::MIN, i32::MAX, Color::White);
+
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let (value, _) = board.minimax(&mut search, 1, i32::MIN, i32::MAX, Color::White);
+        assert!(horizon_value > -50);
+        assert!(value < -50);
+        assert_eq!(board.figure_map.len(), 5);
+    }
+
+    #[test]
+    fn quiescence_without_captures_is_evaluation() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let value = board.quiescence(&mut search, i32::MIN, i32::MAX, Color::White, 0);
+        assert_eq!(value, board.evaluate(&EvalWeights::default()));
+    }
+
+    #[test]
+    fn stops_at_max_depth_with_enough_time() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let result =
+            board.iterative_deepening(&mut search, Duration::from_secs(60), 3, Color::White);
+        assert_eq!(result.depth(), 3);
+        assert_eq!(result.best_move().len(), 1);
+    }
+
+    #[test]
+    fn stop_signal_ends_search_after_first_iteration() {
+        let stop = AtomicBool::new(true);
+        let mut depths = vec![];
+        let mut game = Game::new();
+        let result = game.think(Duration::from_secs(60), 10, &stop, |result| {
+            depths.push(result.depth())
+        });
+        assert_eq!(result.depth(), 1);
+        assert_eq!(result.best_move().len(), 1);
+
+        stop.store(false, Ordering::Relaxed);
+        let result = game.think(Duration::from_secs(60), 3, &stop, |result| {
+            depths.push(result.depth())
+        });
+        assert_eq!(result.depth(), 3);
+        assert_eq!(depths, [1, 1, 2, 3]);
+    }
+
+    #[test]
+    fn result_has_a_playable_principal_variation() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let result =
+            board.iterative_deepening(&mut search, Duration::from_secs(60), 4, Color::White);
+        assert!(!result.pv().is_empty() && result.pv().len() <= 4);
+        assert_eq!(result.pv()[0][0].square_no, result.best_move()[0].square_no);
+        assert!(result.score().abs() < EvalWeights::default().man);
+        assert!(result.nodes() > 0);
+        assert_eq!(board.figure_map.len(), 40);
+
+        let mut game = Game::new();
+        for moves in result.pv() {
+            assert!(game.make_move(moves.clone()).is_ok());
+        }
+    }
+
+    #[test]
+    fn search_scores_running_into_a_move_limit_as_a_draw() {
+        let king = |color: &str| IFigure {
+            color: color.to_string(),
+            kind: "king".to_string(),
+        };
+        let mut figure_map = HashMap::from([
+            (1, king("white")),
+            (3, king("white")),
+            (5, king("white")),
+            (96, king("black")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let (score, _) = board.minimax(&mut search, 2, i32::MIN, i32::MAX, Color::White);
+        assert!(score > 0);
+
+        //Next move is the 16th of the three kings
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        search.positions = PositionHistory::new(0, &board.position);
+        for key in 1..32 {
+            search.positions.push(key, true, false, &board.position);
+        }
+        let (score, mov) = board.minimax(&mut search, 2, i32::MIN, i32::MAX, Color::White);
+        assert_eq!(score, 0);
+        assert!(mov.is_some());
+    }
+
+    #[test]
+    fn draws_of_the_searched_line_are_not_reused_by_other_lines() {
+        let king = |color: &str| IFigure {
+            color: color.to_string(),
+            kind: "king".to_string(),
+        };
+        let mut figure_map = HashMap::from([
+            (1, king("white")),
+            (3, king("white")),
+            (5, king("white")),
+            (96, king("black")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        search.positions = PositionHistory::new(0, &board.position);
+        for key in 1..32 {
+            search.positions.push(key, true, false, &board.position);
+        }
+        let (score, _) = board.minimax(&mut search, 2, i32::MIN, i32::MAX, Color::White);
+        assert_eq!(score, 0);
+        drop(search);
+
+        //Same position without the moves played before is no draw
+        let mut search = Search::new(&mut tt);
+        let (score, _) = board.minimax(&mut search, 2, i32::MIN, i32::MAX, Color::White);
+        assert!(score > 0);
+    }
+
+    #[test]
+    fn win_scores_are_stored_relative_to_the_node() {
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        //Win 3 plies below a node 4 plies from the root
+        search.ply = 4;
+        let stored = search.value_to_tt(WIN_SCORE - 7);
+        assert_eq!(stored, WIN_SCORE - 3);
+        search.ply = 2;
+        assert_eq!(search.value_from_tt(stored), WIN_SCORE - 5);
+        assert_eq!(search.value_from_tt(-stored), -(WIN_SCORE - 5));
+        assert_eq!(search.value_to_tt(150), 150);
+    }
+
+    #[test]
+    fn multi_pv_ranks_root_moves() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let lines = board.multi_pv(&mut search, Duration::from_millis(200), 3, Color::Black);
+        assert_eq!(lines.len(), 3);
+        //Black is better with lower scores
+        assert!(lines
+            .windows(2)
+            .all(|pair| pair[0].score() <= pair[1].score()));
+        let first_moves: Vec<(i32, i32)> = lines
+            .iter()
+            .map(|line| (line.pv()[0][0].moved_figure_no, line.pv()[0][0].square_no))
+            .collect();
+        assert!(first_moves
+            .iter()
+            .enumerate()
+            .all(|(line_no, mov)| !first_moves[..line_no].contains(mov)));
+        assert_eq!(board.figure_map.len(), 40);
+    }
+
+    #[test]
+    fn multi_pv_lists_only_mandatory_captures() {
+        //White has to take 54, no matter how many lines are asked for
+        let mut figure_map: HashMap<i32, IFigure> = HashMap::from([
+            (63, figure("white")),
+            (90, figure("white")),
+            (54, figure("black")),
+            (36, figure("black")),
+            (27, figure("black")),
+        ]);
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let lines = board.multi_pv(&mut search, Duration::from_millis(100), 5, Color::White);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].pv()[0][0].captured_figure_no, Some(54));
+    }
+
+    #[test]
+    fn returns_move_of_last_completed_iteration_when_time_is_up() {
+        let mut figure_map = initial_figure_map();
+        let mut board = Board::new(&mut figure_map);
+        let mut tt = TranspositionTable::default();
+        let mut search = Search::new(&mut tt);
+        let start = Instant::now();
+        let result = board.iterative_deepening(
+            &mut search,
+            Duration::from_millis(50),
+            MAX_SEARCH_DEPTH,
+            Color::Black,
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(result.depth() >= 1 && result.depth() < MAX_SEARCH_DEPTH);
+        let legal_moves = board.legal_moves(&Color::Black);
+        assert!(legal_moves
+            .iter()
+            .any(|mov| mov[0].square_no == result.best_move()[0].square_no));
+    }
+
+    //Start position and a few openings, each reached by picking moves in generation order
+    fn benchmark_positions() -> Vec<(HashMap<i32, IFigure>, Color)> {
+        let openings: [&[usize]; 4] = [&[], &[2], &[4, 3], &[1, 6, 8, 2]];
+        openings
+            .iter()
+            .map(|opening| {
+                let mut figure_map = initial_figure_map();
+                let mut board = Board::new(&mut figure_map);
+                let mut color = Color::White;
+                for &choice in opening.iter() {
+                    let moves = board.legal_moves(&color);
+                    board.make_moves(&moves[choice % moves.len()]);
+                    color = color.opponent();
+                }
+                (figure_map, color)
+            })
+            .collect()
+    }
+
+    fn benchmark_nodes(move_ordering: bool) -> u64 {
+        benchmark_positions()
+            .into_iter()
+            .map(|(mut figure_map, color)| {
+                let mut board = Board::new(&mut figure_map);
+                let mut tt = TranspositionTable::default();
+                let mut search = Search::new(&mut tt);
+                search.move_ordering = move_ordering;
+                board.iterative_deepening(&mut search, Duration::from_secs(600), 5, color);
+                search.nodes()
+            })
+            .sum()
+    }
+
+    //Run with `cargo test --release search_benchmark -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn search_benchmark() {
+        let start = Instant::now();
+        let nodes: u64 = benchmark_positions()
+            .into_iter()
+            .map(|(mut figure_map, color)| {
+                let mut board = Board::new(&mut figure_map);
+                let mut tt = TranspositionTable::default();
+                let mut search = Search::new(&mut tt);
+                board.iterative_deepening(&mut search, Duration::from_secs(600), 8, color);
+                search.nodes()
+            })
+            .sum();
+        let elapsed = start.elapsed();
+        let nodes_per_second = nodes as f64 / elapsed.as_secs_f64();
+        println!("search benchmark: {nodes} nodes in {elapsed:?}, {nodes_per_second:.0} nodes/s");
+        println!(
+            "depth 5 nodes: {} unordered, {} ordered",
+            benchmark_nodes(false),
+            benchmark_nodes(true)
+        );
+    }
+
+    #[test]
+    fn move_ordering_searches_fewer_nodes() {
+        let unordered = benchmark_nodes(false);
+        let ordered = benchmark_nodes(true);
+        assert!(ordered < unordered);
+    }
+
+    #[test]
+    fn move_generation_order_is_deterministic() {
+        let mut figure_map = initial_figure_map();
+        let board = Board::new(&mut figure_map);
+        let first_squares = |moves: Vec<Vec<Move>>| -> Vec<(i32, i32)> {
+            moves
+                .iter()
+                .map(|mov| (mov[0].moved_figure_no, mov[0].square_no))
+                .collect()
+        };
+        let moves = first_squares(board.legal_moves(&Color::White));
+        let mut sorted_moves = moves.clone();
+        sorted_moves.sort_by_key(|(figure_no, _)| *figure_no);
+        assert_eq!(moves, sorted_moves);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::bitboard::{bit_square, square_bit, BitMove};
+use crate::{Color, IFigure};
+use std::mem;
+
+pub(crate) const DEFAULT_HASH_SIZE_MB: usize = 16;
+
+//splitmix64, keys have to be the same in every build so they are generated at compile time
+const fn zobrist_keys() -> [[u64; 100]; 4] {
+    let mut keys = [[0; 100]; 4];
+    let mut state: u64 = 0x5EED_C4EC_5EED_C4EC;
+    let mut kind = 0;
+    while kind < 4 {
+        let mut sqare_no = 0;
+        while sqare_no < 100 {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut key = state;
+            key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            keys[kind][sqare_no] = key ^ (key >> 31);
+            sqare_no += 1;
+        }
+        kind += 1;
+    }
+    keys
+}
+
+static ZOBRIST_KEYS: [[u64; 100]; 4] = zobrist_keys();
+const BLACK_TO_MOVE_KEY: u64 = 0xA3C5_9AC2_4D6B_1E07;
+
+/// Zobrist key of a single figure standing on the square. Figures `Position::put` ignores,
+/// off the board or of an unknown color, don't change the key either.
+pub(crate) fn figure_key(sqare_no: i32, figure: &IFigure) -> u64 {
+    let color = match figure.color.as_str() {
+        "white" => Color::White,
+        "black" => Color::Black,
+        _ => return 0,
+    };
+    square_bit(sqare_no).map_or(0, |bit| bit_key(bit, color, figure.kind == "king"))
+}
+
+/// Zobrist key of a figure standing on a bit of the bitboards.
+pub(crate) fn bit_key(bit: u32, color: Color, is_king: bool) -> u64 {
+    let kind = match (color, is_king) {
+        (Color::White, false) => 0,
+        (Color::White, true) => 1,
+        (Color::Black, false) => 2,
+        (Color::Black, true) => 3,
+    };
+    ZOBRIST_KEYS[kind][bit_square(bit) as usize]
+}
+
+/// Position key extended with the side to move.
+pub(crate) fn position_key(hash: u64, color: &Color) -> u64 {
+    match color {
+        Color::White => hash,
+        Color::Black => hash ^ BLACK_TO_MOVE_KEY,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Bound {
+    Exact,
+    //Value is at least this good, search failed high
+    Lower,
+    //Value is at most this good, search failed low
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Entry {
+    pub(crate) key: u64,
+    pub(crate) depth: i32,
+    pub(crate) value: i32,
+    pub(crate) bound: Bound,
+    pub(crate) best_move: Option<BitMove>,
+}
+
+/// Fixed-size table of searched positions, sized from a memory budget.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::with_memory(DEFAULT_HASH_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// Table taking about `megabytes` of memory.
+    pub fn with_memory(megabytes: usize) -> Self {
+        let entries_no = (megabytes * 1024 * 1024 / mem::size_of::<Option<Entry>>()).max(1);
+        TranspositionTable {
+            entries: vec![None; entries_no],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    pub(crate) fn probe(&self, key: u64) -> Option<&Entry> {
+        self.entries[self.index(key)]
+            .as_ref()
+            .filter(|entry| entry.key == key)
+    }
+
+    pub(crate) fn store(&mut self, entry: Entry) {
+        let index = self.index(entry.key);
+        //Deeper results of the same position are kept, other positions are replaced
+        let replace = match &self.entries[index] {
+            Some(old_entry) => old_entry.key != entry.key || old_entry.depth <= entry.depth,
+            None => true,
+        };
+        if replace {
+            self.entries[index] = Some(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: u64, depth: i32) -> Entry {
+        Entry {
+            key,
+            depth,
+            value: depth,
+            bound: Bound::Exact,
+            best_move: None,
+        }
+    }
+
+    #[test]
+    fn figures_ignored_by_the_bitboards_are_ignored_by_the_key() {
+        let figure = |color: &str, kind: &str| IFigure {
+            color: color.to_string(),
+            kind: kind.to_string(),
+        };
+        assert_eq!(figure_key(-5, &figure("white", "man")), 0);
+        assert_eq!(figure_key(150, &figure("black", "king")), 0);
+        assert_eq!(figure_key(44, &figure("white", "man")), 0);
+        assert_eq!(figure_key(63, &figure("red", "king")), 0);
+        let bit = square_bit(63).unwrap();
+        assert_eq!(
+            figure_key(63, &figure("black", "king")),
+            bit_key(bit, Color::Black, true)
+        );
+    }
+
+    #[test]
+    fn table_size_follows_memory_budget() {
+        let small = TranspositionTable::with_memory(1);
+        let big = TranspositionTable::with_memory(4);
+        assert_eq!(big.len() / small.len(), 4);
+        assert!(small.len() * mem::size_of::<Option<Entry>>() <= 1024 * 1024);
+        assert_eq!(TranspositionTable::with_memory(0).len(), 1);
+        assert!(small.is_empty());
+    }
+
+    #[test]
+    fn deeper_entry_of_same_position_is_kept() {
+        let mut table = TranspositionTable::with_memory(1);
+        let key = 12345;
+        table.store(entry(key, 5));
+        table.store(entry(key, 3));
+        assert_eq!(table.probe(key).unwrap().depth, 5);
+        table.store(entry(key, 7));
+        assert_eq!(table.probe(key).unwrap().depth, 7);
+
+        let other_key = key + table.len() as u64;
+        table.store(entry(other_key, 1));
+        assert!(table.probe(key).is_none());
+        assert_eq!(table.probe(other_key).unwrap().depth, 1);
+
+        table.clear();
+        assert!(table.is_empty());
+    }
+}
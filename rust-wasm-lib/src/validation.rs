@@ -0,0 +1,356 @@
+use crate::notation::move_squares;
+use crate::{Board, Color, IFigure, Move};
+use core::fmt;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const MoveError: &'static str = r#"
+export interface MoveError extends Error {
+    code: "OFF_BOARD" | "EMPTY_SQUARE" | "NOT_YOUR_TURN" | "WRONG_COLOR" | "CAPTURE_IS_MANDATORY"
+        | "FEWER_THAN_MAX_CAPTURES" | "BLOCKED_SQUARE" | "ILLEGAL_MOVE" | "INVALID_FIGURE",
+}
+"#;
+
+/// Reason why a move can't be applied to the position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    OffBoard,
+    EmptySquare,
+    NotYourTurn,
+    WrongColor,
+    CaptureIsMandatory,
+    FewerThanMaxCaptures,
+    BlockedSquare,
+    IllegalMove,
+    InvalidFigure,
+}
+
+impl MoveError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MoveError::OffBoard => "OFF_BOARD",
+            MoveError::EmptySquare => "EMPTY_SQUARE",
+            MoveError::NotYourTurn => "NOT_YOUR_TURN",
+            MoveError::WrongColor => "WRONG_COLOR",
+            MoveError::CaptureIsMandatory => "CAPTURE_IS_MANDATORY",
+            MoveError::FewerThanMaxCaptures => "FEWER_THAN_MAX_CAPTURES",
+            MoveError::BlockedSquare => "BLOCKED_SQUARE",
+            MoveError::IllegalMove => "ILLEGAL_MOVE",
+            MoveError::InvalidFigure => "INVALID_FIGURE",
+        }
+    }
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            MoveError::OffBoard => "square is not a playable square of the board",
+            MoveError::EmptySquare => "there is no figure on the moved square",
+            MoveError::NotYourTurn => "figure belongs to the player who is not on move",
+            MoveError::WrongColor => "move doesn't match the color of the figures on the board",
+            MoveError::CaptureIsMandatory => "capture is mandatory",
+            MoveError::FewerThanMaxCaptures => "capture sequence must take the most figures",
+            MoveError::BlockedSquare => "target square is occupied",
+            MoveError::IllegalMove => "figure can't move that way",
+            MoveError::InvalidFigure => "figure has to be a white or black man or king",
+        };
+        write!(f, "{message}")
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+impl From<MoveError> for JsValue {
+    fn from(error: MoveError) -> Self {
+        let js_error: JsValue = JsError::new(&error.to_string()).into();
+        //Machine-readable code next to the message
+        let _ = js_sys::Reflect::set(&js_error, &"code".into(), &error.code().into());
+        js_error
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const apply_move: &'static str = r#"
+export function apply_move(color: Color, figure_map: Map<number, IFigure>, moves: Move[]): Map<number, IFigure>;
+"#;
+
+#[wasm_bindgen(skip_typescript)]
+pub fn apply_move(color: Color, figure_map: JsValue, moves: JsValue) -> Result<JsValue, JsValue> {
+    let mut figure_map: HashMap<i32, IFigure> = figure_map_from_js::<JsValue>(figure_map)?;
+    let moves: Vec<Move> = serde_wasm_bindgen::from_value(moves)?;
+    let mut board: Board = Board::new(&mut figure_map);
+    board.apply_move(&color, &moves)?;
+    Ok(serde_wasm_bindgen::to_value(&figure_map)?)
+}
+
+/// Figure map coming from JS, every figure has to stand on a playable square and be
+/// a white or black man or king.
+pub(crate) fn figure_map_from_js<E>(figure_map: JsValue) -> Result<HashMap<i32, IFigure>, E>
+where
+    E: From<serde_wasm_bindgen::Error> + From<MoveError>,
+{
+    let figure_map: HashMap<i32, IFigure> = serde_wasm_bindgen::from_value(figure_map)?;
+    check_figure_map(&figure_map)?;
+    Ok(figure_map)
+}
+
+fn check_figure_map(figure_map: &HashMap<i32, IFigure>) -> Result<(), MoveError> {
+    for (sqare_no, figure) in figure_map {
+        if !is_playable(*sqare_no) {
+            return Err(MoveError::OffBoard);
+        }
+        let color = matches!(figure.color.as_str(), "white" | "black");
+        let kind = matches!(figure.kind.as_str(), "man" | "king");
+        if !color || !kind {
+            return Err(MoveError::InvalidFigure);
+        }
+    }
+    Ok(())
+}
+
+fn is_playable(sqare_no: i32) -> bool {
+    (0..100).contains(&sqare_no) && (sqare_no / 10 + sqare_no % 10) % 2 == 1
+}
+
+impl Board<'_> {
+    /// Checks the move against the rules and makes it, returns the move as played by the engine.
+    pub(crate) fn apply_move(
+        &mut self,
+        color: &Color,
+        moves: &[Move],
+    ) -> Result<Vec<Move>, MoveError> {
+        let legal_moves = self.check_move(color, moves)?;
+        self.make_moves(&legal_moves);
+        Ok(legal_moves)
+    }
+
+    fn check_move(&mut self, color: &Color, moves: &[Move]) -> Result<Vec<Move>, MoveError> {
+        let first_mov = moves.first().ok_or(MoveError::IllegalMove)?;
+        for mov in moves {
+            let captured_figure_no = mov.captured_figure_no.unwrap_or(mov.square_no);
+            if ![mov.moved_figure_no, mov.square_no, captured_figure_no]
+                .into_iter()
+                .all(is_playable)
+            {
+                return Err(MoveError::OffBoard);
+            }
+        }
+        if moves
+            .windows(2)
+            .any(|movs| movs[0].square_no != movs[1].moved_figure_no)
+        {
+            return Err(MoveError::IllegalMove);
+        }
+
+        let figure = self
+            .figure_map
+            .get(&first_mov.moved_figure_no)
+            .ok_or(MoveError::EmptySquare)?;
+        let color_str = match color {
+            Color::White => "white",
+            Color::Black => "black",
+        };
+        if figure.color != color_str {
+            return Err(MoveError::NotYourTurn);
+        }
+        let captures_own_figure = moves
+            .iter()
+            .filter_map(|mov| mov.captured_figure_no)
+            .filter_map(|captured_figure_no| self.figure_map.get(&captured_figure_no))
+            .any(|captured_figure| captured_figure.color == color_str);
+        if first_mov.moved_figure.color != figure.color || captures_own_figure {
+            return Err(MoveError::WrongColor);
+        }
+        //Moved figure leaves its square, so a capture sequence may come back to it
+        if moves.iter().any(|mov| {
+            mov.square_no != first_mov.moved_figure_no
+                && self.figure_map.contains_key(&mov.square_no)
+        }) {
+            return Err(MoveError::BlockedSquare);
+        }
+
+        //Captures taking the same figures by another route end in the same position,
+        //only one route of them is generated
+        let legal_moves = self.legal_moves(color);
+        let squares = move_squares(moves);
+        if let Some(legal_mov) = legal_moves
+            .iter()
+            .find(|legal_mov| legal_mov.len() == moves.len() && move_squares(legal_mov) == squares)
+        {
+            return Ok(legal_mov.clone());
+        }
+
+        let max_captures = legal_moves
+            .first()
+            .filter(|legal_mov| legal_mov[0].captured_figure_no.is_some())
+            .map_or(0, |legal_mov| legal_mov.len());
+        let captures = moves
+            .iter()
+            .filter(|mov| mov.captured_figure_no.is_some())
+            .count();
+        if max_captures > 0 && captures == 0 {
+            Err(MoveError::CaptureIsMandatory)
+        } else if captures > 0 && captures < max_captures {
+            Err(MoveError::FewerThanMaxCaptures)
+        } else {
+            Err(MoveError::IllegalMove)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figure(color: &str, kind: &str) -> IFigure {
+        IFigure {
+            color: color.to_string(),
+            kind: kind.to_string(),
+        }
+    }
+
+    fn step(figure_map: &HashMap<i32, IFigure>, from: i32, to: i32, captured: Option<i32>) -> Move {
+        Move {
+            moved_figure_no: from,
+            moved_figure: figure_map.get(&from).cloned().unwrap_or_default(),
+            square_no: to,
+            captured_figure_no: captured,
+            captured_figure: captured.and_then(|no| figure_map.get(&no).cloned()),
+            promoted: false,
+        }
+    }
+
+    fn position() -> HashMap<i32, IFigure> {
+        HashMap::from([
+            (63, figure("white", "man")),
+            (54, figure("black", "man")),
+            (34, figure("black", "man")),
+            (67, figure("white", "man")),
+            (76, figure("white", "man")),
+        ])
+    }
+
+    #[test]
+    fn figures_off_the_board_or_of_unknown_kind_are_rejected() {
+        assert_eq!(check_figure_map(&position()), Ok(()));
+        for sqare_no in [-11, 0, 100, 150] {
+            let figure_map = HashMap::from([(sqare_no, figure("white", "man"))]);
+            assert_eq!(check_figure_map(&figure_map), Err(MoveError::OffBoard));
+        }
+        for (color, kind) in [("red", "man"), ("white", "queen")] {
+            let figure_map = HashMap::from([(63, figure(color, kind))]);
+            assert_eq!(check_figure_map(&figure_map), Err(MoveError::InvalidFigure));
+        }
+    }
+
+    #[test]
+    fn applies_legal_capture_sequence() {
+        let mut figure_map = position();
+        let moves = vec![
+            step(&figure_map, 63, 45, Some(54)),
+            Move {
+                moved_figure_no: 45,
+                ..step(&figure_map, 63, 23, Some(34))
+            },
+        ];
+        let mut board = Board::new(&mut figure_map);
+        assert!(board.apply_move(&Color::White, &moves).is_ok());
+        assert!(figure_map.contains_key(&23));
+        assert!(!figure_map.contains_key(&54) && !figure_map.contains_key(&34));
+    }
+
+    #[test]
+    fn capture_by_another_route_than_the_generated_one_is_legal() {
+        //Man goes round the four black men and comes back to 63, either way round
+        let ring = || {
+            HashMap::from([
+                (63, figure("white", "man")),
+                (54, figure("black", "man")),
+                (34, figure("black", "man")),
+                (32, figure("black", "man")),
+                (52, figure("black", "man")),
+            ])
+        };
+        let route = |figure_map: &HashMap<i32, IFigure>, squares: [(i32, i32); 4]| {
+            let mut from = 63;
+            squares
+                .iter()
+                .map(|&(captured, to)| {
+                    let mov = Move {
+                        moved_figure_no: from,
+                        ..step(figure_map, 63, to, Some(captured))
+                    };
+                    from = to;
+                    mov
+                })
+                .collect::<Vec<Move>>()
+        };
+        for squares in [
+            [(54, 45), (34, 23), (32, 41), (52, 63)],
+            [(52, 41), (32, 23), (34, 45), (54, 63)],
+        ] {
+            let mut figure_map = ring();
+            let moves = route(&figure_map, squares);
+            let mut board = Board::new(&mut figure_map);
+            assert!(board.apply_move(&Color::White, &moves).is_ok());
+            assert_eq!(figure_map.len(), 1);
+        }
+        let mut figure_map = ring();
+        let mut moves = route(&figure_map, [(54, 45), (34, 23), (32, 41), (52, 63)]);
+        moves.pop();
+        let mut board = Board::new(&mut figure_map);
+        assert_eq!(
+            board.apply_move(&Color::White, &moves).unwrap_err(),
+            MoveError::FewerThanMaxCaptures
+        );
+    }
+
+    #[test]
+    fn reports_each_kind_of_illegal_move() {
+        let mut figure_map = position();
+        let jump = |to: i32, captured: i32| Move {
+            moved_figure_no: 45,
+            ..step(&figure_map, 63, to, Some(captured))
+        };
+        let cases = [
+            (vec![step(&figure_map, 63, 53, None)], MoveError::OffBoard),
+            (
+                vec![step(&figure_map, 65, 56, None)],
+                MoveError::EmptySquare,
+            ),
+            (
+                vec![step(&figure_map, 54, 65, None)],
+                MoveError::NotYourTurn,
+            ),
+            (
+                vec![Move {
+                    moved_figure: figure("black", "man"),
+                    ..step(&figure_map, 67, 58, None)
+                }],
+                MoveError::WrongColor,
+            ),
+            (
+                vec![step(&figure_map, 67, 58, None)],
+                MoveError::CaptureIsMandatory,
+            ),
+            (
+                vec![step(&figure_map, 63, 45, Some(54))],
+                MoveError::FewerThanMaxCaptures,
+            ),
+            (
+                vec![step(&figure_map, 67, 76, None)],
+                MoveError::BlockedSquare,
+            ),
+            (
+                vec![step(&figure_map, 63, 45, Some(54)), jump(27, 36)],
+                MoveError::IllegalMove,
+            ),
+        ];
+        let mut board = Board::new(&mut figure_map);
+        for (moves, error) in cases {
+            assert_eq!(board.apply_move(&Color::White, &moves).err(), Some(error));
+        }
+        assert_eq!(board.figure_map.len(), 5);
+    }
+}
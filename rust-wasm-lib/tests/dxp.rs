@@ -0,0 +1,61 @@
+//! Plays a match between two instances of the DXP binary over loopback.
+
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Output, Stdio};
+
+fn dxp(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_dxp"));
+    command.args(args).stdout(Stdio::piped());
+    command
+}
+
+//Result line and PDN printed after the game
+fn game_report(output: &str) -> (&str, &str) {
+    let start = output.find("Result ").expect("game was played");
+    output[start..].split_once('\n').unwrap()
+}
+
+fn play(connect_args: &[&str]) -> (String, String) {
+    let mut listener = dxp(&["--listen", "127.0.0.1:0", "--move-time", "5"])
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(listener.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    let address = line
+        .trim()
+        .strip_prefix("Listening on ")
+        .unwrap()
+        .to_string();
+
+    let mut args = vec!["--connect", &address, "--move-time", "5"];
+    args.extend_from_slice(connect_args);
+    let Output {
+        status,
+        stdout: initiator,
+        ..
+    } = dxp(&args).output().unwrap();
+    assert!(status.success());
+    let mut follower = String::new();
+    stdout.read_to_string(&mut follower).unwrap();
+    assert!(listener.wait().unwrap().success());
+    (String::from_utf8(initiator).unwrap(), follower)
+}
+
+#[test]
+fn both_sides_agree_on_a_full_game() {
+    let (initiator, follower) = play(&[]);
+    let (result, pdn) = game_report(&initiator);
+    assert_eq!(game_report(&follower), (result, pdn));
+    assert!(["Result 2-0", "Result 0-2", "Result 1-1"].contains(&result));
+    assert!(pdn.contains("1. "));
+}
+
+#[test]
+fn game_starts_from_the_requested_position() {
+    let (initiator, follower) = play(&["--fen", "W:W32,50:B1,19,28", "--color", "white"]);
+    let (result, pdn) = game_report(&follower);
+    assert_eq!(game_report(&initiator), (result, pdn));
+    assert!(pdn.contains("[FEN \"W:W32,50:B1,19,28\"]"));
+    assert!(pdn.contains("1. 32x23x14"));
+}
@@ -0,0 +1,138 @@
+//! Drives the Hub engine binary through its stdin and stdout like a GUI would.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Engine {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hub"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("hub binary starts");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Engine {
+            child,
+            stdin,
+            stdout,
+        }
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.stdin, "{line}").unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        assert!(self.stdout.read_line(&mut line).unwrap() > 0, "engine quit");
+        line.trim_end().to_string()
+    }
+
+    //Lines up to and including the first one starting with `prefix`
+    fn read_until(&mut self, prefix: &str) -> Vec<String> {
+        let mut lines = vec![];
+        loop {
+            let line = self.read_line();
+            let found = line.starts_with(prefix);
+            lines.push(line);
+            if found {
+                return lines;
+            }
+        }
+    }
+}
+
+#[test]
+fn handshake_and_ping() {
+    let mut engine = Engine::start();
+    engine.send("hub");
+    let lines = engine.read_until("wait");
+    assert!(lines[0].starts_with("id name="));
+    assert!(lines[0].ends_with("author=kubuseg"), "{}", lines[0]);
+    engine.send("init");
+    assert_eq!(engine.read_line(), "ready");
+    engine.send("ping");
+    assert_eq!(engine.read_line(), "pong");
+    engine.send("quit");
+    assert!(engine.child.wait().unwrap().success());
+}
+
+#[test]
+fn thinks_to_the_given_depth() {
+    let mut engine = Engine::start();
+    engine.send("init");
+    engine.read_until("ready");
+    engine.send("new-game");
+    engine.send("pos moves=\"32-28 19-23\"");
+    engine.send("level depth=4");
+    engine.send("go think");
+    let lines = engine.read_until("done");
+    let infos: Vec<&String> = lines
+        .iter()
+        .filter(|line| line.starts_with("info"))
+        .collect();
+    assert_eq!(infos.len(), 4);
+    assert!(infos[3].starts_with("info depth=4 score="));
+    assert!(infos[3].contains(" pv=\"28x19x23"));
+    //Only capture is forced, the engine has to take back
+    let done = lines.last().unwrap();
+    assert!(done.starts_with("done move=28x19x23"), "{done}");
+    engine.send("quit");
+    assert!(engine.child.wait().unwrap().success());
+}
+
+#[test]
+fn captures_are_written_with_the_captured_squares() {
+    let mut engine = Engine::start();
+    let mut squares = vec!['e'; 50];
+    squares[31] = 'w';
+    squares[27] = 'b';
+    squares[18] = 'b';
+    squares[0] = 'b';
+    let position: String = std::iter::once('W').chain(squares).collect();
+    engine.send(&format!("pos pos={position}"));
+    engine.send("level depth=1");
+    engine.send("go think");
+    let done = engine.read_until("done").pop().unwrap();
+    assert!(done.starts_with("done move=32x14x19x28"), "{done}");
+    engine.send("quit");
+    assert!(engine.child.wait().unwrap().success());
+}
+
+#[test]
+fn stop_ends_analysis_and_ping_is_answered_meanwhile() {
+    let mut engine = Engine::start();
+    engine.send("go analyze");
+    engine.read_until("info depth=1 ");
+    engine.send("ping");
+    engine.read_until("pong");
+    engine.send("stop");
+    let done = engine.read_until("done").pop().unwrap();
+    assert!(done.starts_with("done move="), "{done}");
+    engine.send("ping");
+    engine.read_until("pong");
+    engine.send("quit");
+    assert!(engine.child.wait().unwrap().success());
+}
+
+#[test]
+fn errors_are_reported_and_the_engine_keeps_running() {
+    let mut engine = Engine::start();
+    engine.send("pos moves=\"32-23\"");
+    assert_eq!(engine.read_line(), "error message=\"illegal move '32-23'\"");
+    engine.send("castle");
+    assert!(engine.read_line().starts_with("error message="));
+    engine.send("ping");
+    assert_eq!(engine.read_line(), "pong");
+    //Closing the input ends the engine as well
+    drop(engine.stdin);
+    assert!(engine.child.wait().unwrap().success());
+}
@@ -1,11 +1,11 @@
-import React, { useCallback, useEffect, useState } from "react";
+import React, { useCallback, useEffect, useRef, useState } from "react";
 import "./App.scss";
 import init, {
   Move,
-  possible_moves,
-  get_winner,
-  forced_moves,
-  get_best_move,
+  Difficulty,
+  Game as CheckersGame,
+  GameOutcome,
+  DrawReason,
 } from "./pkg/rust_wasm_lib";
 import blackCrown from "./blackCrown.svg";
 import whiteCrown from "./whiteCrown.svg";
@@ -88,145 +88,158 @@ function Board(props: IBoardProps) {
 }
 
 export default function Game() {
+  const gameRef = useRef<CheckersGame | null>(null);
   const [figureMap, setFigureMap] = useState<Map<number, IFigure>>(
     getInitialFiguresState()
   );
   const [selectedFigureNo, setSelectedFigureNo] = useState<number | null>(null);
+  const [playedMoves, setPlayedMoves] = useState<Move[]>([]);
   const [whiteIsNext, setWhiteIsNext] = useState<boolean>(true);
   const [possibleMoves, setPossibleMoves] = useState<Move[]>([]);
-  const [winner, setWinner] = useState<Color | null>(null);
+  const [outcome, setOutcome] = useState<GameOutcome>({ kind: "ongoing" });
   const [isPlayerMode, setIsPlayerMode] = useState<boolean>(false);
   const [selectedColor, setSelectedColor] = useState<Color>(Color.White);
+  const [difficulty, setDifficulty] = useState<Difficulty>(Difficulty.Medium);
+  const [isThinking, setIsThinking] = useState<boolean>(false);
 
-  useEffect(() => {
+  const syncWithGame = useCallback(() => {
+    const game = gameRef.current;
+    if (!game) return;
+    setFigureMap(game.figure_map());
+    setWhiteIsNext(game.side_to_move === Color.White);
+    setOutcome(game.outcome());
+    setSelectedFigureNo(null);
+    setPlayedMoves([]);
+  }, []);
+
+  const startNewGame = useCallback(() => {
     init().then(() => {
-      //Get possible for selectedFigure
-      const possibleMoves = selectedFigureNo
-        ? possible_moves(selectedFigureNo, figureMap)
-        : [];
-      const forcedMoves = selectedFigureNo
-        ? forced_moves(whiteIsNext ? Color.White : Color.Black, figureMap)
-        : [];
-      if (forcedMoves.length) {
-        setPossibleMoves(
-          possibleMoves.filter((move) =>
-            forcedMoves.some((fMove) => lodash.isEqual(fMove, move))
-          )
-        );
-      } else setPossibleMoves(possibleMoves);
+      gameRef.current?.free();
+      gameRef.current = new CheckersGame();
+      syncWithGame();
     });
-  }, [figureMap, selectedFigureNo, whiteIsNext]);
-
-  const makeMove = useCallback(
-    (move: Move) => {
-      const newFigureMap = figureMap;
+  }, [syncWithGame]);
 
-      //Move selected figure
-      newFigureMap.delete(move.moved_figure_no);
-      newFigureMap.set(move.square_no, move.moved_figure);
-
-      let isMultiCaptureScenario: boolean = false;
-      if (move.captured_figure_no) {
-        //Delete captured figure
-        newFigureMap.delete(move.captured_figure_no);
-        //Check if multi-capture scenario is happening
-        isMultiCaptureScenario = possible_moves(
-          move.square_no,
-          newFigureMap
-        ).some((move) => move.captured_figure_no);
-      }
+  useEffect(() => {
+    startNewGame();
+  }, [startNewGame]);
 
-      if (isMultiCaptureScenario) {
-        setSelectedFigureNo(move.square_no);
-      } else {
-        setSelectedFigureNo(null);
-        setWhiteIsNext(!whiteIsNext);
-        setPossibleMoves([]);
-        //If there is a multi-capture  scenario figure doesn't become king
-        if (becomesKing(move.square_no, move.moved_figure)) {
-          newFigureMap.set(move.square_no, {
-            kind: "king",
-            color: move.moved_figure.color,
-          });
-        }
-      }
-      setFigureMap(newFigureMap);
-      setWinner(get_winner(newFigureMap));
-    },
-    [figureMap, whiteIsNext]
-  );
+  useEffect(() => {
+    //Get next steps of legal moves for selected figure
+    const game = gameRef.current;
+    if (!game || selectedFigureNo === null) {
+      setPossibleMoves([]);
+      return;
+    }
+    const movedFigureNo = playedMoves[0]?.moved_figure_no ?? selectedFigureNo;
+    setPossibleMoves(
+      game
+        .legal_moves()
+        .filter(
+          (moves) =>
+            moves[0].moved_figure_no === movedFigureNo &&
+            lodash.isEqual(moves.slice(0, playedMoves.length), playedMoves)
+        )
+        .map((moves) => moves[playedMoves.length])
+    );
+  }, [figureMap, selectedFigureNo, playedMoves]);
 
   useEffect(() => {
-    init().then(() => {
-      //Bot move
-      if (!isPlayerMode && !isPlayerTurn(whiteIsNext, selectedColor)) {
-        const bestMove = get_best_move(
-          selectedColor === Color.White ? Color.Black : Color.White,
-          figureMap
-        );
-        bestMove.forEach((move) => makeMove(move));
-      }
-    });
-  }, [figureMap, makeMove, whiteIsNext, isPlayerMode, selectedColor]);
+    //Bot move
+    const game = gameRef.current;
+    if (
+      game &&
+      outcome.kind === "ongoing" &&
+      !isPlayerMode &&
+      !isPlayerTurn(whiteIsNext, selectedColor)
+    ) {
+      //Search blocks the page, so it starts only once the last move is painted
+      setIsThinking(true);
+      let timeout: ReturnType<typeof setTimeout> | undefined;
+      const frame = requestAnimationFrame(() => {
+        timeout = setTimeout(() => {
+          if (gameRef.current === game) {
+            game.make_move(game.engine_move(difficulty));
+            syncWithGame();
+          }
+          setIsThinking(false);
+        });
+      });
+      return () => {
+        cancelAnimationFrame(frame);
+        clearTimeout(timeout);
+        setIsThinking(false);
+      };
+    }
+  }, [whiteIsNext, outcome, isPlayerMode, selectedColor, difficulty, syncWithGame]);
 
   const handleClick = (
     clickedSquareNo: number,
     clickedSqareFigure: IFigure | null
   ) => {
+    const game = gameRef.current;
     if (
-      winner ||
+      !game ||
+      outcome.kind !== "ongoing" ||
       (!isPlayerMode && !isPlayerTurn(whiteIsNext, selectedColor))
     ) {
       return;
     }
     if (
-      figureMap.get(clickedSquareNo)?.color ===
-      (whiteIsNext ? "white" : "black")
+      !playedMoves.length &&
+      clickedSqareFigure?.color === (whiteIsNext ? "white" : "black")
     ) {
       setSelectedFigureNo(
-        selectedFigureNo && selectedFigureNo === clickedSquareNo
-          ? null
-          : clickedSquareNo
+        selectedFigureNo === clickedSquareNo ? null : clickedSquareNo
       );
+      return;
     }
 
-    let move = possibleMoves.find((move) => move.square_no === clickedSquareNo);
-    if (selectedFigureNo && move) {
-      makeMove(move);
+    const move = possibleMoves.find((move) => move.square_no === clickedSquareNo);
+    if (selectedFigureNo === null || !move) {
+      return;
+    }
+    const newPlayedMoves = [...playedMoves, move];
+    const isSequenceComplete = game
+      .legal_moves()
+      .some((moves) => lodash.isEqual(moves, newPlayedMoves));
+    if (isSequenceComplete) {
+      game.make_move(newPlayedMoves);
+      syncWithGame();
+    } else {
+      //Captured figures stay on the board until the capture sequence ends
+      const newFigureMap = new Map(figureMap);
+      newFigureMap.delete(move.moved_figure_no);
+      newFigureMap.set(move.square_no, move.moved_figure);
+      setFigureMap(newFigureMap);
+      setPlayedMoves(newPlayedMoves);
+      setSelectedFigureNo(move.square_no);
     }
   };
 
   const handleBotModeClick = () => {
     if (isPlayerMode) {
-      setFigureMap(getInitialFiguresState());
-      setSelectedFigureNo(null);
       setIsPlayerMode(false);
-      setWhiteIsNext(true);
+      startNewGame();
     }
   };
 
   const handlePlayerModeClick = () => {
     if (!isPlayerMode) {
-      setFigureMap(getInitialFiguresState());
-      setSelectedFigureNo(null);
       setIsPlayerMode(true);
-      setWhiteIsNext(true);
+      startNewGame();
     }
   };
 
   const handleResetGameClick = () => {
-    setFigureMap(getInitialFiguresState());
-    setSelectedFigureNo(null);
-    setWhiteIsNext(true);
+    startNewGame();
   };
 
   const onColorRadioButtonChange = (
     event: React.ChangeEvent<HTMLInputElement>
   ) => {
     setSelectedColor(strToColor(event.target.value));
-    setFigureMap(getInitialFiguresState());
-    setSelectedFigureNo(null);
-    setWhiteIsNext(true);
+    startNewGame();
   };
 
   return (
@@ -249,13 +262,26 @@ export default function Game() {
         />
         Black
       </div>
+      <select
+        className="difficulty-select"
+        value={difficulty}
+        onChange={(event) => setDifficulty(Number(event.target.value))}
+      >
+        <option value={Difficulty.Beginner}>Beginner</option>
+        <option value={Difficulty.Easy}>Easy</option>
+        <option value={Difficulty.Medium}>Medium</option>
+        <option value={Difficulty.Hard}>Hard</option>
+        <option value={Difficulty.Expert}>Expert</option>
+      </select>
       <div className="game-board">
         <div
           className="status"
-          style={{ color: winner ? colorToStr(winner) : (whiteIsNext ? "white" : "black") }}
+          style={{ color: outcomeColor(outcome) ?? (whiteIsNext ? "white" : "black") }}
         >
-          {winner
-            ? `The winner is ${winner}!`
+          {outcome.kind !== "ongoing"
+            ? outcomeToStr(outcome)
+            : isThinking
+            ? "Thinking..."
             : "Next player: " + (whiteIsNext ? "White" : "Black")}
         </div>
         <Board
@@ -291,15 +317,35 @@ const strToColor = (color: string) => {
   return color === "white" ? Color.White : Color.Black;
 };
 
-const colorToStr = (color: Color) => {
-  return color === Color.White ? "white" : "black";
+const outcomeColor = (outcome: GameOutcome) => {
+  switch (outcome.kind) {
+    case "white_wins":
+      return "white";
+    case "black_wins":
+      return "black";
+    default:
+      return null;
+  }
+};
+
+const drawReasons: Record<DrawReason, string> = {
+  threefold_repetition: "threefold repetition",
+  twenty_five_move_rule: "the 25-move rule",
+  sixteen_move_rule: "the 16-move rule",
+  five_move_rule: "the 5-move rule",
 };
 
-const becomesKing = (sqareNo: number, figure: IFigure): boolean => {
-  if (figure.kind === "king") return false;
-  return figure.color === "black"
-    ? [...Array(10).keys()].map((i) => i + 90).includes(sqareNo)
-    : [...Array(10).keys()].includes(sqareNo);
+const outcomeToStr = (outcome: GameOutcome) => {
+  switch (outcome.kind) {
+    case "white_wins":
+      return "The winner is white!";
+    case "black_wins":
+      return "The winner is black!";
+    case "draw":
+      return `Draw by ${drawReasons[outcome.reason]}!`;
+    default:
+      return "";
+  }
 };
 
 const getInitialFiguresState = (): Map<number, IFigure> => {